@@ -1,17 +1,36 @@
 use std::{
     fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::audio_resource::AudioResource;
+use crate::error::SoursError;
+use crate::fade::{Faded, Fader};
+use crate::output_device::{self, Devices};
+use crate::playback_position::{Counted, PlaybackClock, PlaybackPosition};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum PlayerAction {
     Play,
     Pause,
+    PlayPause,
     Stop,
     Next,
     Previous,
+    /// Skip ahead by this much
+    Forward(Duration),
+    /// Go back by this much
+    Back(Duration),
+    /// Jump to this position in the current resource
+    Seek(Duration),
+    /// Set the volume in percent
+    SetVolume(u8),
     None,
 }
 
@@ -21,106 +40,413 @@ impl Default for PlayerAction {
     }
 }
 
+/// How long pausing and stopping fade out, if enabled
+const PAUSE_FADE: Duration = Duration::from_millis(400);
+
+/// A resource queued behind the current one, so it follows without a gap
+struct Preloaded {
+    resource: AudioResource,
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
 pub struct AudioPlayer {
     pub current: Option<AudioResource>,
     last_action: PlayerAction,
-    start_time: Option<Instant>,
-    pause_time: Option<std::time::Instant>,
-    paused_duration: Duration,
-    sink: rodio::Sink,
-    _stream: rodio::OutputStream,
-    _stream_handle: rodio::OutputStreamHandle,
+    clock: PlaybackClock,
+    // Ids tell the clock which queued source is playing
+    next_id: u64,
+    preloaded: Option<Preloaded>,
+    // The resource the preloaded one took over from, until `advance` reports it
+    taken_over: Option<Option<AudioResource>>,
+    // `None` without an output to play to. rodio's idle sinks can't stand in,
+    // clearing one waits for sources nothing ever plays
+    sink: Option<rodio::Sink>,
+    volume: f32,
+    // Gain of the sources in `sink`
+    fader: Fader,
+    // Sinks of tracks that are fading out after being replaced or stopped
+    fading: Vec<rodio::Sink>,
+    crossfade: Duration,
+    fade_pause_stop: bool,
+    // When a fading pause is silent and the sink can actually be paused
+    pending_pause: Option<Instant>,
+    device: Option<String>,
+    // Name of the system default when it was opened, to notice the system switching away
+    default_device: Option<String>,
+    output: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
 }
 
 impl AudioPlayer {
-    pub fn play(&mut self, resource: AudioResource) {
-        if let Some(current) = &self.current {
-            if current == &resource {
-                if let Some(pause_time) = self.pause_time {
-                    self.paused_duration += pause_time.elapsed();
+    pub fn new(device: Option<String>) -> (Self, Result<(), rodio::StreamError>) {
+        let mut player = Self {
+            current: None,
+            last_action: PlayerAction::None,
+            clock: PlaybackClock::default(),
+            next_id: 0,
+            preloaded: None,
+            taken_over: None,
+            sink: None,
+            volume: 1.0,
+            fader: Fader::new(1.0),
+            fading: Vec::new(),
+            crossfade: Duration::ZERO,
+            fade_pause_stop: false,
+            pending_pause: None,
+            device: None,
+            default_device: None,
+            output: None,
+        };
+        let result = player.set_device(device);
+        (player, result)
+    }
+    pub fn set_device(&mut self, device: Option<String>) -> Result<(), rodio::StreamError> {
+        /*
+            Move playback onto the output device called `device` (`None` for the system default),
+            the playing resource continues at the same position.
+            If the device can't be opened the system default is used instead
+        */
+        let (result, output, device) = match output_device::open_output(device.as_deref()) {
+            Ok(output) => (Ok(()), Some(output), device),
+            Err(e) => (Err(e), output_device::open_output(None).ok(), None),
+        };
+
+        self.take_over();
+        let resume = self
+            .current
+            .clone()
+            .map(|resource| (resource, self.clock.elapsed(), self.sink_paused()));
+
+        self.default_device = match (&device, &output) {
+            (None, Some(_)) => output_device::default_device_name(),
+            _ => None,
+        };
+        self.output = output;
+        self.device = device;
+        self.sink = self.new_sink();
+        self.preloaded = None;
+        // Fading sinks play to the old device
+        self.fading.clear();
+
+        if let Some((resource, elapsed, paused)) = resume {
+            if self.sink.is_some() && self.append_from(&resource, elapsed).is_ok() {
+                if !paused {
+                    self.play_sink();
                 }
-                self.pause_time = None;
-                self.sink.play();
-                return;
-            } else if self.start_time.is_none() {
-                self.restart();
+            } else {
+                self.stop();
+            }
+        }
+
+        result
+    }
+    pub fn check_device(&mut self, wanted: Option<&str>, devices: &Devices) -> bool {
+        /*
+            Fall back to the default device if the used one disappeared,
+            and switch back once the wanted device is available again.
+            Playing on the default follows it when the system changes it,
+            e.g. because the one in use was unplugged.
+            Returns whether the device was changed
+        */
+        let lost = match &self.device {
+            Some(name) => !devices.available(name),
+            None => self.output.is_none() || devices.default != self.default_device,
+        };
+        let returned = match wanted {
+            Some(name) => self.device.as_deref() != Some(name) && devices.available(name),
+            None => false,
+        };
+
+        if returned {
+            let _ = self.set_device(wanted.map(String::from));
+        } else if lost {
+            let _ = self.set_device(None);
+        }
+        lost || returned
+    }
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+    pub fn has_output(&self) -> bool {
+        self.output.is_some()
+    }
+    pub fn play(&mut self, resource: AudioResource) -> Result<(), SoursError> {
+        if self.sink.is_none() {
+            return Ok(());
+        }
+        if let Some(current) = &self.current {
+            if current == &resource && !self.sink_empty() {
+                self.resume();
+                return Ok(());
             }
         }
-        self.stop();
-        self.restart();
-        self.current = Some(resource.clone());
-        self.sink.append(resource.decoder());
-        self.sink.play();
+
+        // Overlap the playing track's tail with the new one's head
+        let crossfade = !self.crossfade.is_zero() && self.is_playing();
+        if crossfade {
+            self.fade_out_current(self.crossfade);
+            self.reset();
+            self.fader = Fader::new(0.0);
+        } else {
+            self.reset();
+        }
+
+        if let Err(e) = self.append_from(&resource, Duration::ZERO) {
+            self.stop();
+            return Err(e);
+        }
+        self.current = Some(resource);
+        self.play_sink();
+        if crossfade {
+            self.fader.fade_to(1.0, self.crossfade);
+        }
         self.last_action = PlayerAction::Play;
+        Ok(())
     }
-    fn restart(&mut self) {
-        self.start_time = Some(Instant::now());
-        self.pause_time = Option::None;
-        self.paused_duration = Duration::new(0, 0);
+    fn resume(&mut self) {
+        let fade = match self.fade_pause_stop {
+            true => PAUSE_FADE,
+            false => Duration::ZERO,
+        };
+        self.pending_pause = None;
+        self.play_sink();
+        self.fader.fade_to(1.0, fade);
+        self.last_action = PlayerAction::Play;
     }
     pub fn pause(&mut self) {
-        self.sink.pause();
+        if self.fade_pause_stop && self.is_playing() {
+            // The sink is paused by `update` once the fade is silent
+            self.fader.fade_to(0.0, PAUSE_FADE);
+            self.pending_pause = Some(Instant::now() + PAUSE_FADE);
+        } else {
+            self.pause_sink();
+        }
         self.last_action = PlayerAction::Pause;
-        self.pause_time = Some(std::time::Instant::now());
     }
     pub fn stop(&mut self) {
-        self.pause_time = None;
-        self.start_time = None;
+        if self.fade_pause_stop && self.is_playing() {
+            self.fade_out_current(PAUSE_FADE);
+        }
+        self.reset();
         self.last_action = PlayerAction::Stop;
+    }
+    fn reset(&mut self) {
         self.current = None;
-        self.sink.pause();
-        self.sink.clear();
+        self.preloaded = None;
+        self.taken_over = None;
+        self.pending_pause = None;
+        if let Some(sink) = &self.sink {
+            sink.pause();
+            sink.clear();
+        }
+        self.fader = Fader::new(1.0);
+        self.clock.reset(Duration::ZERO);
+    }
+    fn fade_out_current(&mut self, duration: Duration) {
+        /*
+            Hand the playing sink over to `fading` where it fades out on its own,
+            playback continues on a fresh sink
+        */
+        self.cancel_preload();
+        self.fader.fade_out_and_end(duration);
+
+        let sink = self.new_sink();
+        let faded = std::mem::replace(&mut self.sink, sink);
+        self.fading.extend(faded);
+    }
+    fn new_sink(&self) -> Option<rodio::Sink> {
+        let (_, handle) = self.output.as_ref()?;
+        let sink = rodio::Sink::try_new(handle).ok()?;
+        sink.set_volume(self.volume);
+        Some(sink)
+    }
+    fn play_sink(&self) {
+        if let Some(sink) = &self.sink {
+            sink.play();
+        }
+    }
+    fn pause_sink(&self) {
+        if let Some(sink) = &self.sink {
+            sink.pause();
+        }
+    }
+    fn sink_empty(&self) -> bool {
+        match &self.sink {
+            Some(sink) => sink.empty(),
+            None => true,
+        }
+    }
+    fn sink_paused(&self) -> bool {
+        self.sink.as_ref().is_some_and(|sink| sink.is_paused())
+    }
+    pub fn update(&mut self) {
+        /*
+            Finish fades that ended since the last call, call this every frame
+        */
+        if self.pending_pause.is_some_and(|at| Instant::now() >= at) {
+            self.pending_pause = None;
+            self.pause_sink();
+        }
+        self.fading.retain(|sink| !sink.empty());
+    }
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+    pub fn set_fade_pause_stop(&mut self, fade: bool) {
+        self.fade_pause_stop = fade;
+    }
+    pub fn seek(&mut self, position: Duration) -> Result<(), SoursError> {
+        /*
+            Rebuild the decoder and skip exactly up to `position`,
+            keeping the current play / pause state
+        */
+        self.take_over();
+        let Some(resource) = self.current.clone() else {
+            return Ok(());
+        };
+        let position = position.min(resource.duration);
+        let paused = self.sink_paused();
+
+        // Clearing the sink also drops the preloaded resource
+        if let Some(sink) = &self.sink {
+            sink.clear();
+        }
+        self.preloaded = None;
+        if let Err(e) = self.append_from(&resource, position) {
+            self.stop();
+            return Err(e);
+        }
+
+        if !paused {
+            self.play_sink();
+        }
+        Ok(())
+    }
+    pub fn skip(&mut self, by: Duration, back: bool) -> Result<(), SoursError> {
+        let Some(position) = self.position() else {
+            return Ok(());
+        };
+        let elapsed = position.elapsed;
+        let target = if back {
+            elapsed.saturating_sub(by)
+        } else {
+            elapsed + by
+        };
+        self.seek(target)
+    }
+    fn append_from(
+        &mut self,
+        resource: &AudioResource,
+        position: Duration,
+    ) -> Result<(), SoursError> {
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+        let source = resource.try_decoder_at(position)?;
+        self.next_id += 1;
+        let source = Counted::new(source, self.next_id, position, self.clock.clone());
+        sink.append(Faded::new(source, self.fader.clone()));
+        Ok(())
+    }
+    pub fn preload(&mut self, resource: AudioResource) -> Result<(), SoursError> {
+        /*
+            Queue `resource` behind the current one, so it starts on the very
+            sample the current one ends. Replaces a previously preloaded resource
+        */
+        if self.current.is_none() || self.preloaded() == Some(&resource) {
+            return Ok(());
+        }
+        self.cancel_preload();
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+
+        self.next_id += 1;
+        let source = Counted::queued(resource.try_decoder()?, self.next_id, self.clock.clone());
+        self.preloaded = Some(Preloaded {
+            resource,
+            id: self.next_id,
+            cancelled: source.cancel_flag(),
+        });
+        sink.append(Faded::new(source, self.fader.clone()));
+        Ok(())
+    }
+    pub fn preloaded(&self) -> Option<&AudioResource> {
+        self.preloaded.as_ref().map(|p| &p.resource)
+    }
+    pub fn cancel_preload(&mut self) {
+        /*
+            The sink can't remove a queued source, so it is skipped instead.
+            One that already started playing isn't preloaded anymore but the current one
+        */
+        self.take_over();
+        if let Some(preloaded) = self.preloaded.take() {
+            preloaded.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+    pub fn advance(&mut self) -> Option<Option<AudioResource>> {
+        /*
+            Make the preloaded resource the current one once its first sample played.
+            Returns the resource it took over from if it did so since the last call,
+            also when that happened while seeking or cancelling the preload
+        */
+        self.take_over();
+        self.taken_over.take()
+    }
+    fn take_over(&mut self) {
+        match self.preloaded.take() {
+            Some(preloaded) if self.clock.source() == preloaded.id => {
+                let previous = self.current.replace(preloaded.resource);
+                self.taken_over = Some(previous);
+            }
+            preloaded => self.preloaded = preloaded,
+        }
     }
     pub fn set_volume(&mut self, volume: f32) {
         let vol = f32::clamp(volume, 0.0, 1.0);
-        self.sink.set_volume(vol);
+        self.volume = vol;
+        for sink in self.sink.iter().chain(&self.fading) {
+            sink.set_volume(vol);
+        }
     }
     pub fn set_volume_100(&mut self, volume: u8) {
-        let vol = f32::clamp(volume as f32 / 100.0, 0.0, 1.0);
-        self.sink.set_volume(vol);
+        self.set_volume(volume as f32 / 100.0);
     }
     pub fn volume(&self) -> f32 {
-        self.sink.volume()
+        self.volume
     }
     pub fn just_finished(&self) -> bool {
-        self.last_action == PlayerAction::Play && !self.is_playing()
+        /*
+            Not finished while a preloaded resource is about to take over
+        */
+        self.last_action == PlayerAction::Play
+            && self.clock.finished()
+            && (self.preloaded.is_none() || self.sink_empty())
     }
     pub fn is_playing(&self) -> bool {
-        !self.sink.empty() && !self.sink.is_paused() && self.current.is_some()
+        !self.sink_empty()
+            && !self.sink_paused()
+            && self.pending_pause.is_none()
+            && self.current.is_some()
     }
-    pub fn elapsed(&self) -> Result<Duration, std::io::Error> {
-        if let Some(start) = self.start_time {
-            let mut elapsed_time = start.elapsed();
-            if let Some(paused_time) = self.pause_time {
-                elapsed_time -= self.paused_duration + paused_time.elapsed();
-            } else {
-                elapsed_time -= self.paused_duration;
-            }
-
-            Ok(elapsed_time)
-        } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Timer not started",
-            ))
-        }
+    pub fn stream_handle(&self) -> Option<&rodio::OutputStreamHandle> {
+        self.output.as_ref().map(|(_, handle)| handle)
+    }
+    pub fn position(&self) -> Option<PlaybackPosition> {
+        let current = self.current.as_ref()?;
+        Some(PlaybackPosition {
+            elapsed: self.clock.elapsed().min(current.duration),
+            total: current.duration,
+        })
     }
 }
 
 impl Default for AudioPlayer {
     fn default() -> Self {
-        let (_stream, _stream_handle) = rodio::OutputStream::try_default().unwrap();
-        let sink = rodio::Sink::try_new(&_stream_handle).unwrap();
-        Self {
-            current: None,
-            last_action: PlayerAction::None,
-            start_time: None,
-            pause_time: None,
-            paused_duration: std::time::Duration::from_secs(0),
-            sink,
-            _stream,
-            _stream_handle,
-        }
+        Self::new(None).0
     }
 }
 
@@ -128,10 +454,118 @@ impl Debug for AudioPlayer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AudioPlayer")
             .field("current", &self.current)
+            .field("preloaded", &self.preloaded())
             .field("last_action", &self.last_action)
-            .field("start_time", &self.start_time)
-            .field("pause_time", &self.pause_time)
-            .field("paused_duration", &self.paused_duration)
+            .field("device", &self.device)
+            .field("position", &self.position())
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    /// A silent 8 kHz mono WAV file
+    fn wav(name: &str, length: Duration) -> AudioResource {
+        const RATE: u32 = 8000;
+        let len = (length.as_millis() as u32 * RATE / 1000) * 2;
+        let mut data = Vec::new();
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&(36 + len).to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&RATE.to_le_bytes());
+        data.extend_from_slice(&(RATE * 2).to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&16u16.to_le_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&len.to_le_bytes());
+        data.resize(data.len() + len as usize, 0);
+
+        let name = format!("sours-player-{}-{}.wav", std::process::id(), name);
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, data).unwrap();
+        AudioResource::new(path, length)
+    }
+
+    /// A player on a sink that a thread plays at about real time instead of a device
+    fn player() -> (AudioPlayer, Arc<AtomicBool>) {
+        let (mut player, _) = AudioPlayer::new(None);
+        let (sink, mut output) = rodio::Sink::new_idle();
+        player.output = None;
+        player.sink = Some(sink);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                for _ in 0..8 {
+                    output.next();
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+        (player, stop)
+    }
+
+    fn until(what: &str, mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn seeking_after_the_preloaded_resource_took_over_seeks_in_it() {
+        let short = wav("short", Duration::from_millis(50));
+        let long = wav("long", Duration::from_secs(5));
+        let (mut player, stop) = player();
+
+        player.play(short.clone()).unwrap();
+        player.preload(long.clone()).unwrap();
+        let id = player.preloaded.as_ref().unwrap().id;
+        until("the preloaded resource", || player.clock.source() == id);
+
+        // `advance` wasn't called yet, the seek has to catch up on its own
+        player.seek(Duration::from_secs(3)).unwrap();
+        assert_eq!(player.current.as_ref(), Some(&long));
+        assert!(player.is_playing());
+        until("the seek", || {
+            player.position().unwrap().elapsed >= Duration::from_secs(3)
+        });
+        assert_eq!(player.advance(), Some(Some(short.clone())));
+        assert_eq!(player.advance(), None);
+
+        stop.store(true, Ordering::Relaxed);
+        for path in [&short.path, &long.path] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn cancelling_a_preload_that_took_over_keeps_it_playing() {
+        let short = wav("cancel-short", Duration::from_millis(50));
+        let long = wav("cancel-long", Duration::from_secs(5));
+        let (mut player, stop) = player();
+
+        player.play(short.clone()).unwrap();
+        player.preload(long.clone()).unwrap();
+        let id = player.preloaded.as_ref().unwrap().id;
+        until("the preloaded resource", || player.clock.source() == id);
+
+        player.cancel_preload();
+        assert_eq!(player.current.as_ref(), Some(&long));
+        assert!(player.is_playing());
+        assert_eq!(player.advance(), Some(Some(short.clone())));
+
+        stop.store(true, Ordering::Relaxed);
+        for path in [&short.path, &long.path] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use lofty::{AudioFile, Probe};
-use rodio::Decoder;
+use crate::cover_art;
+use crate::decoder::{self, AudioSource};
+use crate::error::SoursError;
+use crate::formats::{self, AudioFormat};
+use crate::metadata::{AudioMetadata, Column};
+use crate::playback_position::mmss;
+use lofty::{AudioFile, Probe, TaggedFile};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     fmt::Debug,
-    fs::File,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -12,61 +16,158 @@ use std::{
 pub struct AudioResource {
     pub path: PathBuf,
     pub duration: Duration,
+    pub format: Option<AudioFormat>,
+    pub metadata: AudioMetadata,
+    pub error: Option<SoursError>,
     playable: bool,
 }
 
 impl AudioResource {
     pub fn new(path: PathBuf, duration: Duration) -> Self {
         let playable = path.exists();
+        let format = formats::probe(&path);
         Self {
             path,
             duration,
+            format,
+            metadata: AudioMetadata::default(),
+            error: None,
             playable,
         }
     }
 
-    pub fn decoder(&self) -> Decoder<File> {
-        Decoder::new(File::open(self.path.clone()).unwrap()).unwrap()
+    pub fn try_decoder(&self) -> Result<AudioSource, SoursError> {
+        self.try_decoder_at(Duration::ZERO)
     }
 
-    pub fn from_path(path: String) -> Self {
-        let path = Path::new(&path).to_path_buf();
-        let (playable, duration);
-        if path.exists() {
-            duration = Self::get_duration(path.to_str().unwrap());
-            playable = true;
-        } else {
-            duration = Duration::from_secs(0);
-            playable = false;
+    /// A decoder starting `position` into the file
+    pub fn try_decoder_at(&self, position: Duration) -> Result<AudioSource, SoursError> {
+        // Fall back to the extension if the content couldn't be probed when loading
+        let format = self
+            .format
+            .or_else(|| AudioFormat::from_extension(self.path.extension()?.to_str()?))
+            .ok_or_else(|| SoursError::UnsupportedFormat(self.path.clone()))?;
+        decoder::try_open(&self.path, format, position)
+    }
+
+    pub fn try_from_path(path: impl AsRef<Path>) -> Result<Self, SoursError> {
+        let path = path.as_ref().to_path_buf();
+        if !path.exists() {
+            return Err(SoursError::NotFound(path));
         }
 
-        Self {
+        let format =
+            formats::probe(&path).ok_or_else(|| SoursError::UnsupportedFormat(path.clone()))?;
+        let tagged = Self::read_tags(&path)?;
+
+        Ok(Self {
             path,
-            duration,
-            playable,
+            duration: tagged.properties().duration(),
+            format: Some(format),
+            metadata: AudioMetadata::from_tagged_file(&tagged),
+            error: None,
+            playable: true,
+        })
+    }
+
+    pub fn from_path(path: String) -> Self {
+        /*
+            Like `try_from_path`, but keeps the resource around with its error
+            so it can still be listed (and saved) while unplayable
+        */
+        let path = Path::new(&path).to_path_buf();
+        match Self::try_from_path(&path) {
+            Ok(resource) => resource,
+            Err(error) => Self {
+                path,
+                duration: Duration::from_secs(0),
+                format: None,
+                metadata: AudioMetadata::default(),
+                error: Some(error),
+                playable: false,
+            },
         }
     }
 
     pub fn formatted_duration(&self) -> String {
-        let secs = self.duration.as_secs();
-        let mins = secs / 60;
-        let secs = secs % 60;
+        mmss(self.duration)
+    }
+
+    fn read_tags(path: &Path) -> Result<TaggedFile, SoursError> {
+        Ok(Probe::open(path)?.read()?)
+    }
+
+    /// The title from the tags, or the file name for untagged files
+    pub fn display_name(&self) -> String {
+        match &self.metadata.title {
+            Some(title) => title.clone(),
+            None => self.file_name(),
+        }
+    }
 
-        format!("{:02}:{:02}", mins, secs)
+    fn file_name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => String::new(),
+        }
     }
 
-    fn get_duration(path: &str) -> Duration {
-        let tagged = Probe::open(path).unwrap().read().unwrap();
-        tagged.properties().duration()
+    /// Path of the cached cover thumbnail, from the embedded picture
+    /// or an image like `folder.jpg` next to the file
+    pub fn cover_art(&self) -> Option<PathBuf> {
+        cover_art::thumbnail(&self.path)
+    }
+
+    /// The text shown in the playlist's `column`, empty if the tag is missing
+    pub fn column_text(&self, column: Column) -> String {
+        let metadata = &self.metadata;
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<u32>| value.map(|n| n.to_string()).unwrap_or_default();
+
+        match column {
+            Column::FileName => self.file_name(),
+            Column::Title => self.display_name(),
+            Column::Artist => text(&metadata.artist),
+            Column::Album => text(&metadata.album),
+            Column::AlbumArtist => text(&metadata.album_artist),
+            Column::Track => number(metadata.track),
+            Column::Disc => number(metadata.disc),
+            Column::Year => number(metadata.year),
+            Column::Genre => text(&metadata.genre),
+            Column::Duration => self.formatted_duration(),
+            Column::Bitrate => match metadata.bitrate {
+                Some(kbps) => format!("{} kbps", kbps),
+                None => String::new(),
+            },
+            Column::SampleRate => match metadata.sample_rate {
+                Some(hz) => format!("{:.1} kHz", hz as f32 / 1000.0),
+                None => String::new(),
+            },
+            Column::Channels => match metadata.channels {
+                Some(channels) => channels.to_string(),
+                None => String::new(),
+            },
+        }
     }
 
     pub fn playable(&self) -> bool {
-        if !self.playable {
-            self.playable
+        if !self.playable || self.error.is_some() {
+            false
         } else {
             self.path.exists()
         }
     }
+
+    /// Why the resource can't be played, if it can't
+    pub fn error_message(&self) -> Option<String> {
+        if let Some(error) = &self.error {
+            Some(error.to_string())
+        } else if !self.path.exists() {
+            Some(SoursError::NotFound(self.path.clone()).to_string())
+        } else {
+            None
+        }
+    }
 }
 
 impl PartialEq for AudioResource {
@@ -75,29 +176,46 @@ impl PartialEq for AudioResource {
     }
 }
 
+/// How a resource is stored in `sours.json`. Older files only stored the path,
+/// newer ones cache what was probed so startup doesn't have to re-read every file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredResource {
+    Path(String),
+    Probed {
+        path: PathBuf,
+        duration: Duration,
+        format: Option<AudioFormat>,
+        #[serde(default)]
+        metadata: AudioMetadata,
+    },
+}
+
 impl<'de> Deserialize<'de> for AudioResource {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct Visitor;
-
-        impl<'de> serde::de::Visitor<'de> for Visitor {
-            type Value = AudioResource;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("a path to an audio file")
-            }
-
-            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-            where
-                E: serde::de::Error,
-            {
-                Ok(AudioResource::from_path(value.to_string()))
+        match StoredResource::deserialize(deserializer)? {
+            StoredResource::Path(path) => Ok(AudioResource::from_path(path)),
+            // Files that went missing since are probed again to record the error
+            StoredResource::Probed { path, .. } if !path.exists() => {
+                Ok(AudioResource::from_path(path.to_string_lossy().to_string()))
             }
+            StoredResource::Probed {
+                path,
+                duration,
+                format,
+                metadata,
+            } => Ok(AudioResource {
+                path,
+                duration,
+                format,
+                metadata,
+                error: None,
+                playable: true,
+            }),
         }
-
-        deserializer.deserialize_str(Visitor)
     }
 }
 
@@ -106,7 +224,20 @@ impl Serialize for AudioResource {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.path.to_str().unwrap())
+        /*
+            Broken resources are stored as a plain path so they get probed again on the next start
+        */
+        let stored = if self.error.is_some() {
+            StoredResource::Path(self.path.to_string_lossy().to_string())
+        } else {
+            StoredResource::Probed {
+                path: self.path.clone(),
+                duration: self.duration,
+                format: self.format,
+                metadata: self.metadata.clone(),
+            }
+        };
+        stored.serialize(serializer)
     }
 }
 
@@ -115,6 +246,9 @@ impl Debug for AudioResource {
         f.debug_struct("AudioResource")
             .field("path", &self.path)
             .field("duration", &self.duration)
+            .field("format", &self.format)
+            .field("metadata", &self.metadata)
+            .field("error", &self.error)
             .finish()
     }
 }
@@ -0,0 +1,90 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use lofty::{PictureType, TaggedFileExt};
+
+use crate::paths;
+
+/// Width and height thumbnails are scaled down to fit into
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Image files next to a track that are used if it has no embedded cover,
+/// in order of preference and compared case-insensitively
+const FOLDER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const FOLDER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// The embedded front cover of `path`, or any embedded picture if there is no front cover
+pub fn embedded(path: &Path) -> Option<Vec<u8>> {
+    let tagged = lofty::read_from_path(path).ok()?;
+    let pictures: Vec<_> = tagged.tags().iter().flat_map(|t| t.pictures()).collect();
+
+    pictures
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or(pictures.first())
+        .map(|p| p.data().to_vec())
+}
+
+/// A cover image like `folder.jpg` in the same directory as `path`
+pub fn from_folder(path: &Path) -> Option<PathBuf> {
+    let images: Vec<PathBuf> = std::fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            let extension = p.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            FOLDER_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+        .collect();
+
+    FOLDER_NAMES.iter().find_map(|name| {
+        images
+            .iter()
+            .find(|p| {
+                let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+                stem.eq_ignore_ascii_case(name)
+            })
+            .cloned()
+    })
+}
+
+/// Path of the cached cover thumbnail of `path` as PNG, extracting it on first use.
+/// Returns `None` if the file has neither an embedded nor a folder cover
+pub fn thumbnail(path: &Path) -> Option<PathBuf> {
+    let thumbnail = thumbnail_path(path);
+    if thumbnail.exists() {
+        return Some(thumbnail);
+    }
+
+    let data = match embedded(path) {
+        Some(data) => data,
+        None => std::fs::read(from_folder(path)?).ok()?,
+    };
+    let image = image::load_from_memory(&data)
+        .ok()?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    std::fs::create_dir_all(thumbnail_dir()).ok()?;
+    image.save(&thumbnail).ok()?;
+    Some(thumbnail)
+}
+
+fn thumbnail_path(path: &Path) -> PathBuf {
+    /*
+        Key the cache on the path and modification time,
+        so a re-tagged file gets a new thumbnail
+    */
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    if let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified()) {
+        modified.hash(&mut hasher);
+    }
+    thumbnail_dir().join(format!("{:016x}.png", hasher.finish()))
+}
+
+/// Where scaled down covers are cached, so they're only extracted once per file
+fn thumbnail_dir() -> PathBuf {
+    paths::get().cache.join("covers")
+}
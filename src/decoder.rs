@@ -0,0 +1,708 @@
+use std::{fs::File, path::Path, time::Duration};
+
+use rodio::Source;
+use symphonia::core::{
+    audio::{SampleBuffer, SignalSpec},
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::{Error as SymphoniaError, SeekErrorKind},
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo, Track},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::{Time, TimeBase},
+};
+
+use crate::error::SoursError;
+use crate::formats::AudioFormat;
+
+/// Any decoded audio, whichever decoder produced it
+pub type AudioSource = Box<dyn Source<Item = f32> + Send>;
+
+/// Opens `path` with the decoder matching `format`, starting `position` into it
+pub fn try_open(
+    path: &Path,
+    format: AudioFormat,
+    position: Duration,
+) -> Result<AudioSource, SoursError> {
+    if !path.exists() {
+        return Err(SoursError::NotFound(path.to_path_buf()));
+    }
+
+    Ok(match format {
+        AudioFormat::Opus => Box::new(OpusSource::new(path, position)?),
+        // rodio's decoders can only skip by decoding everything before the position,
+        // symphonia reads their formats too and seeks in the container instead
+        format if format.rodio_native() && position.is_zero() => {
+            let decoder = rodio::Decoder::new(File::open(path)?)?;
+            Box::new(decoder.convert_samples::<f32>())
+        }
+        _ => Box::new(SymphoniaSource::new(path, position)?),
+    })
+}
+
+/// The container of `path` and its first audio track
+fn open_track(path: &Path) -> Result<(Box<dyn FormatReader>, Track), SymphoniaError> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(SymphoniaError::Unsupported("no audio track"))?
+        .clone();
+    Ok((format, track))
+}
+
+/// Seeks `format` to the timestamp `ts` of the track `track_id`. Readers stop on a packet
+/// boundary at or before `ts`, returns where that is, or `None` if `ts` is past the end
+fn seek(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    ts: u64,
+) -> Result<Option<u64>, SymphoniaError> {
+    match format.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id }) {
+        Ok(seeked) => Ok(Some(seeked.actual_ts.min(ts))),
+        Err(SymphoniaError::SeekError(SeekErrorKind::OutOfRange)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decodes the formats rodio can't (AAC/M4A and AIFF) with symphonia,
+/// and the others if playback starts somewhere in the middle
+pub struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn symphonia::core::codecs::Decoder>,
+    track_id: u32,
+    spec: SignalSpec,
+    buffer: Option<SampleBuffer<f32>>,
+    pos: usize,
+    total_duration: Option<Duration>,
+}
+
+impl SymphoniaSource {
+    pub fn new(path: &Path, position: Duration) -> Result<Self, SymphoniaError> {
+        let (format, track) = open_track(path)?;
+
+        let params = &track.codec_params;
+        let spec = SignalSpec::new(
+            params.sample_rate.unwrap_or(44100),
+            params
+                .channels
+                .ok_or(SymphoniaError::Unsupported("unknown channel layout"))?,
+        );
+        let total_duration = match (params.time_base, params.n_frames) {
+            (Some(time_base), Some(frames)) => {
+                let time = time_base.calc_time(frames);
+                Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+            }
+            _ => None,
+        };
+        let decoder = symphonia::default::get_codecs().make(params, &DecoderOptions::default())?;
+
+        let mut source = Self {
+            format,
+            decoder,
+            track_id: track.id,
+            spec,
+            buffer: None,
+            pos: 0,
+            total_duration,
+        };
+        if position.is_zero() {
+            // Decode the first packet right away, rodio reads the frame length before any sample
+            source.decode_next();
+            return Ok(source);
+        }
+
+        // Without a time base timestamps count frames
+        let time_base = params.time_base.unwrap_or(TimeBase::new(1, spec.rate));
+        let target = time_base.calc_timestamp(Time::from(position));
+        let Some(actual) = seek(source.format.as_mut(), track.id, target)? else {
+            // Past the end, the source stays empty
+            return Ok(source);
+        };
+
+        // The reader stopped on a packet boundary, what lies between it and the position is dropped
+        let early = time_base.calc_time(target - actual);
+        let frames = early.seconds * spec.rate as u64 + (early.frac * spec.rate as f64) as u64;
+        source.decode_next();
+        source.discard(frames);
+        Ok(source)
+    }
+
+    fn decode_next(&mut self) -> bool {
+        /*
+            Decode packets until one yields samples, false once the stream ended
+        */
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => {
+                    self.buffer = None;
+                    return false;
+                }
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A corrupt packet is skipped, everything else ends playback
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(_) => {
+                    self.buffer = None;
+                    return false;
+                }
+            };
+            if decoded.frames() == 0 {
+                continue;
+            }
+
+            self.spec = *decoded.spec();
+            let mut buffer = SampleBuffer::new(decoded.capacity() as u64, self.spec);
+            buffer.copy_interleaved_ref(decoded);
+            self.buffer = Some(buffer);
+            self.pos = 0;
+            return true;
+        }
+    }
+
+    fn discard(&mut self, mut frames: u64) {
+        while let Some(len) = self.buffer.as_ref().map(|b| b.len()) {
+            let channels = self.spec.channels.count();
+            let skipped = ((len - self.pos) / channels).min(frames as usize);
+            self.pos += skipped * channels;
+            frames -= skipped as u64;
+
+            if self.pos >= len {
+                self.decode_next();
+            }
+            if frames == 0 {
+                return;
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let buffer = self.buffer.as_ref()?;
+        let sample = buffer.samples()[self.pos];
+        self.pos += 1;
+
+        // The next packet is decoded as soon as this one is through, so the frame
+        // length is only 0 once the stream ended
+        if self.pos >= buffer.len() {
+            self.decode_next();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for SymphoniaSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        // The channels and sample rate may change with the next packet
+        Some(self.buffer.as_ref().map_or(0, |b| b.len() - self.pos))
+    }
+    fn channels(&self) -> u16 {
+        self.spec.channels.count() as u16
+    }
+    fn sample_rate(&self) -> u32 {
+        self.spec.rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+/// Decodes Ogg Opus, which symphonia can only demux, with libopus
+pub struct OpusSource {
+    format: Box<dyn FormatReader>,
+    track_id: u32,
+    decoder: audiopus::coder::Decoder,
+    channels: u16,
+    /// Samples still to drop from the next packets: the encoder delay at the start
+    /// of the stream, or what lies before the position after a seek
+    skip: usize,
+    buffer: Vec<f32>,
+    len: usize,
+    pos: usize,
+}
+
+impl OpusSource {
+    /// Opus always decodes at 48kHz
+    const SAMPLE_RATE: u32 = 48000;
+    /// Samples per channel of the longest possible opus packet (120ms)
+    const MAX_FRAME: usize = 5760;
+    /// How far before the position decoding starts after a seek,
+    /// the decoder needs 80ms to converge (RFC 7845, section 4.6)
+    const PRE_ROLL: u64 = 3840;
+
+    pub fn new(path: &Path, position: Duration) -> Result<Self, SoursError> {
+        let (format, track) = open_track(path)?;
+        let params = &track.codec_params;
+
+        let channels = match params.channels.map(|c| c.count()) {
+            Some(1) => audiopus::Channels::Mono,
+            Some(2) => audiopus::Channels::Stereo,
+            _ => {
+                return Err(SoursError::Decode(
+                    "only mono and stereo opus is supported".to_string(),
+                ))
+            }
+        };
+        let decoder = audiopus::coder::Decoder::new(audiopus::SampleRate::Hz48000, channels)
+            .map_err(|e| SoursError::Decode(e.to_string()))?;
+        let channels = channels as u16;
+
+        let mut source = Self {
+            format,
+            track_id: track.id,
+            decoder,
+            channels,
+            skip: 0,
+            buffer: vec![0.0; Self::MAX_FRAME * channels as usize],
+            len: 0,
+            pos: 0,
+        };
+
+        // Timestamps count the encoder delay too, which is dropped from the output
+        let rate = Self::SAMPLE_RATE as u64;
+        let delay = params.delay.unwrap_or(0) as u64;
+        let target = delay
+            + position.as_secs() * rate
+            + position.subsec_nanos() as u64 * rate / 1_000_000_000;
+        let start = match position.is_zero() {
+            true => Some(0),
+            false => seek(
+                source.format.as_mut(),
+                track.id,
+                target.saturating_sub(Self::PRE_ROLL),
+            )?,
+        };
+        // Past the end the source stays empty
+        if let Some(start) = start {
+            source.skip = (target - start.min(target)) as usize * channels as usize;
+            source.decode_next();
+        }
+        Ok(source)
+    }
+
+    fn decode_next(&mut self) -> bool {
+        /*
+            Decode packets until one yields samples that aren't skipped,
+            false once the stream ended
+        */
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => {
+                    self.len = 0;
+                    self.pos = 0;
+                    return false;
+                }
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let Ok(input) = packet.buf().try_into() else {
+                continue;
+            };
+            let Ok(output) = (&mut self.buffer).try_into() else {
+                self.len = 0;
+                self.pos = 0;
+                return false;
+            };
+
+            let frames = match self.decoder.decode_float(Some(input), output, false) {
+                Ok(frames) => frames,
+                Err(_) => continue,
+            };
+            self.len = frames * self.channels as usize;
+
+            let skipped = self.skip.min(self.len);
+            self.skip -= skipped;
+            self.pos = skipped;
+
+            if self.pos < self.len {
+                return true;
+            }
+        }
+    }
+}
+
+impl Iterator for OpusSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos >= self.len {
+            return None;
+        }
+
+        // Like `SymphoniaSource`, decode ahead so the end shows in the frame length
+        let sample = self.buffer[self.pos];
+        self.pos += 1;
+        if self.pos >= self.len {
+            self.decode_next();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for OpusSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.len - self.pos)
+    }
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A file in the temporary directory that's removed again when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("sours-{}-{}", std::process::id(), name));
+            std::fs::write(&path, data).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Mono 16 bit AIFF, which symphonia decodes
+    fn aiff(rate: u32, samples: &[i16]) -> Vec<u8> {
+        // The sample rate is an 80 bit extended float
+        let exponent = 31 - rate.leading_zeros();
+        let mut comm = Vec::new();
+        comm.extend(1i16.to_be_bytes());
+        comm.extend((samples.len() as u32).to_be_bytes());
+        comm.extend(16i16.to_be_bytes());
+        comm.extend((16383 + exponent as u16).to_be_bytes());
+        comm.extend(((rate as u64) << (63 - exponent)).to_be_bytes());
+
+        let mut ssnd = vec![0; 8];
+        ssnd.extend(samples.iter().flat_map(|s| s.to_be_bytes()));
+
+        let mut form = b"AIFF".to_vec();
+        for (id, chunk) in [(b"COMM", comm), (b"SSND", ssnd)] {
+            form.extend(id);
+            form.extend((chunk.len() as u32).to_be_bytes());
+            form.extend(chunk);
+        }
+        let mut file = b"FORM".to_vec();
+        file.extend((form.len() as u32).to_be_bytes());
+        file.extend(form);
+        file
+    }
+
+    /// Mono Ogg Opus of `samples`, padded to whole 20ms packets
+    fn opus(samples: &[f32]) -> Vec<u8> {
+        use audiopus::{coder::Encoder, Application, Channels, SampleRate};
+        use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+        let encoder =
+            Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Audio).unwrap();
+        let pre_skip = encoder.lookahead().unwrap() as u16;
+
+        let mut head = b"OpusHead".to_vec();
+        head.extend([1, 1]);
+        head.extend(pre_skip.to_le_bytes());
+        head.extend(48000u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(4u32.to_le_bytes());
+        tags.extend(b"test");
+        tags.extend(0u32.to_le_bytes());
+
+        let mut writer = PacketWriter::new(Vec::new());
+        writer
+            .write_packet(head.into(), 1, PacketWriteEndInfo::EndPage, 0)
+            .unwrap();
+        writer
+            .write_packet(tags.into(), 1, PacketWriteEndInfo::EndPage, 0)
+            .unwrap();
+
+        let mut samples = samples.to_vec();
+        samples.resize(samples.len().div_ceil(960) * 960, 0.0);
+        let packets = samples.chunks(960).count();
+        for (i, frame) in samples.chunks(960).enumerate() {
+            let mut packet = vec![0; 4000];
+            let len = encoder.encode_float(frame, &mut packet).unwrap();
+            packet.truncate(len);
+            let end = match i + 1 == packets {
+                true => PacketWriteEndInfo::EndStream,
+                false => PacketWriteEndInfo::EndPage,
+            };
+            writer
+                .write_packet(packet.into(), 1, end, (i as u64 + 1) * 960)
+                .unwrap();
+        }
+        writer.into_inner()
+    }
+
+    /// `silence` seconds of silence, then one second of a tone
+    fn silence_then_tone(silence: f32) -> Vec<f32> {
+        let start = (silence * 48000.0) as usize;
+        (0..start + 48000)
+            .map(|i| match i < start {
+                true => 0.0,
+                false => (i as f32 * 440.0 / 48000.0 * std::f32::consts::TAU).sin() * 0.5,
+            })
+            .collect()
+    }
+
+    /// Samples before the tone starts
+    fn until_tone(source: impl Iterator<Item = f32>) -> usize {
+        source.take_while(|sample| sample.abs() < 0.1).count()
+    }
+
+    /// MPEG-1 layer III frames of silence, 128kbit/s mono at 44.1kHz
+    fn mp3(frames: usize) -> Vec<u8> {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0xc0]);
+        frame.repeat(frames)
+    }
+
+    /// FLAC of 4096 sample blocks, each one constant value, 16 bit mono at 44.1kHz
+    fn flac(blocks: usize) -> Vec<u8> {
+        let crc8 = |data: &[u8]| {
+            data.iter().fold(0u8, |crc, byte| {
+                (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+                    0 => crc << 1,
+                    _ => (crc << 1) ^ 0x07,
+                })
+            })
+        };
+        let crc16 = |data: &[u8]| {
+            data.iter().fold(0u16, |crc, byte| {
+                (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| match crc & 0x8000 {
+                    0 => crc << 1,
+                    _ => (crc << 1) ^ 0x8005,
+                })
+            })
+        };
+
+        let mut file = b"fLaC".to_vec();
+        file.extend([0x80, 0, 0, 34]);
+        file.extend(4096u16.to_be_bytes());
+        file.extend(4096u16.to_be_bytes());
+        file.extend([0; 6]);
+        file.extend(((44100u64 << 44) | (15 << 36) | (blocks as u64 * 4096)).to_be_bytes());
+        file.extend([0; 16]);
+
+        for block in 0..blocks {
+            let mut frame = vec![0xff, 0xf8, 0xc9, 0x08, block as u8];
+            frame.push(crc8(&frame));
+            frame.push(0);
+            frame.extend((block as i16 * 1000).to_be_bytes());
+            frame.extend(crc16(&frame).to_be_bytes());
+            file.extend(frame);
+        }
+        file
+    }
+
+    /// Decodes all of `data` as `format` from `position` on, and returns how many
+    /// samples that gave. Fails the test if decoding panics or doesn't finish
+    fn decode(
+        name: &str,
+        data: &[u8],
+        format: AudioFormat,
+        position: Duration,
+    ) -> Result<usize, SoursError> {
+        let file = TempFile::new(name, data);
+        let path = file.0.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let result = try_open(&path, format, position).map(|source| source.count());
+            let _ = sender.send(result);
+        });
+
+        match receiver.recv_timeout(Duration::from_secs(10)) {
+            Ok(result) => result,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => panic!("decoding {} hung", name),
+            Err(_) => panic!("decoding {} panicked", name),
+        }
+    }
+
+    /// Samples that tell their own position
+    fn ramp(frames: usize) -> Vec<i16> {
+        (0..frames).map(|i| (i % 16384) as i16).collect()
+    }
+
+    #[test]
+    fn symphonia_source_has_a_frame_before_the_first_sample() {
+        let file = TempFile::new("frame.aiff", &aiff(8000, &ramp(8000)));
+        let source = SymphoniaSource::new(&file.0, Duration::ZERO).unwrap();
+        assert!(source.current_frame_len().is_some_and(|len| len > 0));
+    }
+
+    #[test]
+    fn skipping_a_symphonia_source_lands_on_the_position() {
+        let file = TempFile::new("skip.aiff", &aiff(8000, &ramp(24000)));
+        let source = try_open(&file.0, AudioFormat::Aiff, Duration::ZERO).unwrap();
+        let mut skipped = source.skip_duration(Duration::from_millis(1500));
+
+        let expected = 12000.0 / 32768.0;
+        let sample = skipped.next().unwrap();
+        assert!(
+            (sample - expected).abs() < 1e-4,
+            "{} != {}",
+            sample,
+            expected
+        );
+    }
+
+    #[test]
+    fn symphonia_source_ends_with_an_empty_frame() {
+        let file = TempFile::new("end.aiff", &aiff(8000, &ramp(1000)));
+        let mut source = SymphoniaSource::new(&file.0, Duration::ZERO).unwrap();
+        assert_eq!(source.by_ref().count(), 1000);
+        assert_eq!(source.current_frame_len(), Some(0));
+    }
+
+    #[test]
+    fn symphonia_source_starts_at_the_position() {
+        let file = TempFile::new("seek.aiff", &aiff(8000, &ramp(24000)));
+        let mut source = try_open(&file.0, AudioFormat::Aiff, Duration::from_millis(1500)).unwrap();
+        assert_eq!(source.next(), Some(12000.0 / 32768.0));
+        assert_eq!(source.count(), 11999);
+    }
+
+    #[test]
+    fn symphonia_source_past_the_end_is_empty() {
+        let file = TempFile::new("past.aiff", &aiff(8000, &ramp(1000)));
+        let source = SymphoniaSource::new(&file.0, Duration::from_secs(10)).unwrap();
+        assert_eq!(source.current_frame_len(), Some(0));
+        assert_eq!(source.count(), 0);
+    }
+
+    #[test]
+    fn opus_source_drops_the_encoder_delay() {
+        let file = TempFile::new("delay.opus", &opus(&silence_then_tone(1.0)));
+        let source = OpusSource::new(&file.0, Duration::ZERO).unwrap();
+        let silence = until_tone(source);
+        assert!(
+            silence.abs_diff(48000) < 240,
+            "{} samples of silence",
+            silence
+        );
+    }
+
+    #[test]
+    fn opus_source_starts_at_the_position() {
+        let file = TempFile::new("seek.opus", &opus(&silence_then_tone(3.0)));
+        let source = OpusSource::new(&file.0, Duration::from_millis(2500)).unwrap();
+        let silence = until_tone(source);
+        assert!(
+            silence.abs_diff(24000) < 240,
+            "{} samples of silence",
+            silence
+        );
+    }
+
+    #[test]
+    fn opus_source_past_the_end_is_empty() {
+        let file = TempFile::new("past.opus", &opus(&silence_then_tone(0.0)));
+        let source = OpusSource::new(&file.0, Duration::from_secs(10)).unwrap();
+        assert_eq!(source.count(), 0);
+    }
+
+    #[test]
+    fn whole_files_decode() {
+        let opus = opus(&silence_then_tone(0.5));
+        let files = [
+            ("whole.mp3", mp3(20), AudioFormat::Mp3),
+            ("whole.flac", flac(4), AudioFormat::Flac),
+            ("whole.opus", opus, AudioFormat::Opus),
+        ];
+        for (name, data, format) in files {
+            for position in [Duration::ZERO, Duration::from_millis(100)] {
+                let samples = decode(name, &data, format, position);
+                assert!(samples.is_ok_and(|n| n > 0), "{} at {:?}", name, position);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_headers_are_errors() {
+        let opus = opus(&silence_then_tone(0.5));
+        let files = [
+            ("header.mp3", &mp3(1)[..3], AudioFormat::Mp3),
+            ("header.flac", &flac(4)[..20], AudioFormat::Flac),
+            ("header.opus", &opus[..40], AudioFormat::Opus),
+        ];
+        for (name, data, format) in files {
+            for position in [Duration::ZERO, Duration::from_millis(100)] {
+                let result = decode(name, data, format, position);
+                assert!(result.is_err(), "{} at {:?}: {:?}", name, position, result);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_streams_end_early() {
+        let mp3 = mp3(20);
+        let flac = flac(4);
+        let opus = opus(&silence_then_tone(0.5));
+        let files = [
+            ("cut.mp3", &mp3[..mp3.len() / 2 + 100], AudioFormat::Mp3),
+            ("cut.flac", &flac[..flac.len() / 2 + 3], AudioFormat::Flac),
+            ("cut.opus", &opus[..opus.len() / 2 + 10], AudioFormat::Opus),
+        ];
+        for (name, data, format) in files {
+            for position in [Duration::ZERO, Duration::from_millis(100)] {
+                // Playing what's there is as fine as refusing the file, as long as it ends
+                let _ = decode(name, data, format, position);
+            }
+        }
+    }
+
+    #[test]
+    fn random_bytes_are_errors() {
+        use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+        let mut data = vec![0; 64 * 1024];
+        StdRng::seed_from_u64(8).fill_bytes(&mut data);
+        for format in AudioFormat::ALL {
+            let name = format!("random.{}", format.extensions()[0]);
+            for position in [Duration::ZERO, Duration::from_millis(100)] {
+                let result = decode(&name, &data, format, position);
+                assert!(result.is_err(), "{} at {:?}: {:?}", name, position, result);
+            }
+        }
+    }
+}
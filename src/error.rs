@@ -0,0 +1,199 @@
+use std::{fmt::Display, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Everything that can go wrong loading or decoding an `AudioResource`,
+/// or importing and exporting playlists of them.
+/// Messages are kept as strings so resources holding an error stay `Clone`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoursError {
+    NotFound(PathBuf),
+    Io(String),
+    UnsupportedFormat(PathBuf),
+    Probe(String),
+    Decode(String),
+    InvalidPlaylist(String),
+    Tag(String),
+}
+
+impl Display for SoursError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "File not found: {}", path.display()),
+            Self::Io(e) => write!(f, "Could not read file: {}", e),
+            Self::UnsupportedFormat(path) => {
+                write!(f, "Unsupported audio format: {}", path.display())
+            }
+            Self::Probe(e) => write!(f, "Could not read audio properties: {}", e),
+            Self::Decode(e) => write!(f, "Could not decode audio: {}", e),
+            Self::InvalidPlaylist(e) => write!(f, "Invalid playlist: {}", e),
+            Self::Tag(e) => write!(f, "Could not write tags: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SoursError {}
+
+impl From<std::io::Error> for SoursError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<lofty::LoftyError> for SoursError {
+    fn from(e: lofty::LoftyError) -> Self {
+        Self::Probe(e.to_string())
+    }
+}
+
+impl From<rodio::decoder::DecoderError> for SoursError {
+    fn from(e: rodio::decoder::DecoderError) -> Self {
+        Self::Decode(e.to_string())
+    }
+}
+
+impl From<symphonia::core::errors::Error> for SoursError {
+    fn from(e: symphonia::core::errors::Error) -> Self {
+        Self::Decode(e.to_string())
+    }
+}
+
+/// Why a yt-dlp download failed, told apart by its exit code and error output
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DownloadError {
+    /// yt-dlp isn't where sours looked for it
+    MissingBinary(PathBuf),
+    Network(String),
+    /// The video is private, removed or doesn't exist
+    Unavailable(String),
+    GeoBlocked(String),
+    /// The video needs a signed in account to confirm the viewer's age
+    AgeRestricted(String),
+    /// Anything else, with yt-dlp's exit code if it exited on its own
+    Other {
+        code: Option<i32>,
+        message: String,
+    },
+}
+
+impl DownloadError {
+    /// What went wrong, from yt-dlp's `code` and `stderr`
+    pub fn classify(code: Option<i32>, stderr: &str) -> Self {
+        /*
+            yt-dlp prints `ERROR: [extractor] id: message` lines, the last one is the reason.
+            Geo and age messages also say "not available", so they're checked first
+        */
+        let message = stderr
+            .lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix("ERROR:"))
+            .or_else(|| stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let lower = message.to_lowercase();
+        let says = |phrases: &[&str]| phrases.iter().any(|p| lower.contains(p));
+
+        if says(&["in your country", "geo restrict", "geo-restrict"]) {
+            Self::GeoBlocked(message)
+        } else if says(&[
+            "confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            Self::AgeRestricted(message)
+        } else if says(&[
+            "unable to download webpage",
+            "urlopen error",
+            "connection",
+            "timed out",
+            "name resolution",
+            "getaddrinfo",
+            "network is unreachable",
+        ]) {
+            Self::Network(message)
+        } else if says(&[
+            "video unavailable",
+            "private video",
+            "has been removed",
+            "is not available",
+            "does not exist",
+            "unsupported url",
+        ]) {
+            Self::Unavailable(message)
+        } else {
+            Self::Other { code, message }
+        }
+    }
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingBinary(path) => write!(f, "yt-dlp not found: {}", path.display()),
+            Self::Network(e) => write!(f, "Network error: {}", e),
+            Self::Unavailable(e) => write!(f, "Video unavailable: {}", e),
+            Self::GeoBlocked(e) => write!(f, "Blocked in your country: {}", e),
+            Self::AgeRestricted(e) => write!(f, "Age restricted: {}", e),
+            Self::Other {
+                code: Some(code),
+                message,
+            } if !message.is_empty() => {
+                write!(f, "yt-dlp failed ({}): {}", code, message)
+            }
+            Self::Other {
+                code: Some(code), ..
+            } => write!(f, "yt-dlp exited with {}", code),
+            Self::Other { message, .. } => write!(f, "yt-dlp failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_errors_are_told_apart() {
+        let classify = |stderr: &str| DownloadError::classify(Some(1), stderr);
+        let geo =
+            "ERROR: [youtube] abc: The uploader has not made this video available in your country";
+        assert!(matches!(classify(geo), DownloadError::GeoBlocked(_)));
+        let age = "ERROR: [youtube] abc: Sign in to confirm your age. This video may be inappropriate for some users.";
+        assert!(matches!(classify(age), DownloadError::AgeRestricted(_)));
+        let network = "ERROR: [youtube] abc: Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution>";
+        assert!(matches!(classify(network), DownloadError::Network(_)));
+        let private = "ERROR: [youtube] abc: Private video. Sign in if you've been granted access";
+        assert!(matches!(classify(private), DownloadError::Unavailable(_)));
+    }
+
+    #[test]
+    fn the_last_error_line_is_the_reason() {
+        let stderr = "ERROR: first\nWARNING: something\nERROR: [youtube] abc: Video unavailable\n";
+        assert_eq!(
+            DownloadError::classify(Some(1), stderr),
+            DownloadError::Unavailable("[youtube] abc: Video unavailable".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_errors_keep_the_exit_code_and_last_line() {
+        assert_eq!(
+            DownloadError::classify(Some(2), "usage: yt-dlp [OPTIONS] URL\nno such option\n\n"),
+            DownloadError::Other {
+                code: Some(2),
+                message: "no such option".to_string()
+            }
+        );
+        assert_eq!(
+            DownloadError::classify(None, ""),
+            DownloadError::Other {
+                code: None,
+                message: String::new()
+            }
+        );
+    }
+}
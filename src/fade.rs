@@ -0,0 +1,156 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source};
+
+/// Controls the gain of the `Faded` sources it was handed to, from any thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Fader {
+    // f32 bits of the gain being faded to
+    target: Arc<AtomicU32>,
+    duration_ns: Arc<AtomicU64>,
+    // Bumped with every fade, so sources start a new ramp from their current gain
+    generation: Arc<AtomicU64>,
+    // End the sources once they're faded out
+    end: Arc<AtomicBool>,
+}
+
+impl Fader {
+    pub fn new(gain: f32) -> Self {
+        Self {
+            target: Arc::new(AtomicU32::new(gain.to_bits())),
+            duration_ns: Arc::default(),
+            generation: Arc::default(),
+            end: Arc::default(),
+        }
+    }
+
+    /// Fade to `gain` over `duration` with an equal-power curve
+    pub fn fade_to(&self, gain: f32, duration: Duration) {
+        self.target.store(gain.to_bits(), Ordering::Relaxed);
+        self.duration_ns
+            .store(duration.as_nanos() as u64, Ordering::Relaxed);
+        self.end.store(false, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Fade to silence over `duration`, then end the sources
+    pub fn fade_out_and_end(&self, duration: Duration) {
+        self.fade_to(0.0, duration);
+        self.end.store(true, Ordering::Relaxed);
+    }
+
+    fn target(&self) -> f32 {
+        f32::from_bits(self.target.load(Ordering::Relaxed))
+    }
+}
+
+/// Applies the gain of a `Fader` to `input`.
+pub(crate) struct Faded<S> {
+    input: S,
+    fader: Fader,
+    generation: u64,
+    gain: f32,
+    from: f32,
+    to: f32,
+    // Position in and length of the running ramp, in samples
+    pos: u64,
+    len: u64,
+}
+
+impl<S: Source> Faded<S>
+where
+    S::Item: Sample,
+{
+    /// Starts at the gain `fader` is currently fading to
+    pub fn new(input: S, fader: Fader) -> Self {
+        let gain = fader.target();
+        Self {
+            input,
+            generation: fader.generation.load(Ordering::Acquire),
+            fader,
+            gain,
+            from: gain,
+            to: gain,
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    fn start_ramp(&mut self) {
+        let duration = Duration::from_nanos(self.fader.duration_ns.load(Ordering::Relaxed));
+        let rate = self.input.sample_rate() as f32 * self.input.channels() as f32;
+
+        self.from = self.gain;
+        self.to = self.fader.target();
+        self.pos = 0;
+        self.len = (duration.as_secs_f32() * rate) as u64;
+    }
+
+    fn step(&mut self) {
+        /*
+            Equal-power curve: rising gains follow a sine, falling ones a cosine,
+            so two tracks crossfading keep a constant loudness
+        */
+        if self.pos >= self.len {
+            self.gain = self.to;
+            return;
+        }
+        self.pos += 1;
+
+        let t = self.pos as f32 / self.len as f32;
+        let curve = match self.to >= self.from {
+            true => (t * FRAC_PI_2).sin(),
+            false => 1.0 - (t * FRAC_PI_2).cos(),
+        };
+        self.gain = self.from + (self.to - self.from) * curve;
+    }
+}
+
+impl<S: Source> Iterator for Faded<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let generation = self.fader.generation.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.generation = generation;
+            self.start_ramp();
+        }
+
+        self.step();
+        let faded_out = self.pos >= self.len && self.gain <= 0.0;
+        if faded_out && self.fader.end.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let sample = self.input.next()?;
+        Some(sample.amplify(self.gain))
+    }
+}
+
+impl<S: Source> Source for Faded<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use lofty::{FileType, Probe};
+use serde::{Deserialize, Serialize};
+
+/// Every audio format sours can play
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    Wav,
+    Flac,
+    Vorbis,
+    Opus,
+    Aac,
+    Aiff,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 7] = [
+        AudioFormat::Mp3,
+        AudioFormat::Wav,
+        AudioFormat::Flac,
+        AudioFormat::Vorbis,
+        AudioFormat::Opus,
+        AudioFormat::Aac,
+        AudioFormat::Aiff,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mp3 => "MP3",
+            Self::Wav => "WAV",
+            Self::Flac => "FLAC",
+            Self::Vorbis => "OGG Vorbis",
+            Self::Opus => "Opus",
+            Self::Aac => "AAC/M4A",
+            Self::Aiff => "AIFF",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Mp3 => &["mp3"],
+            Self::Wav => &["wav"],
+            Self::Flac => &["flac"],
+            Self::Vorbis => &["ogg", "oga"],
+            Self::Opus => &["opus"],
+            Self::Aac => &["m4a", "aac", "mp4"],
+            Self::Aiff => &["aiff", "aif"],
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let extension = extension.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    fn from_file_type(file_type: FileType) -> Option<Self> {
+        match file_type {
+            FileType::Mpeg => Some(Self::Mp3),
+            FileType::Wav => Some(Self::Wav),
+            FileType::Flac => Some(Self::Flac),
+            FileType::Vorbis => Some(Self::Vorbis),
+            FileType::Opus => Some(Self::Opus),
+            FileType::Aac | FileType::Mp4 => Some(Self::Aac),
+            FileType::Aiff => Some(Self::Aiff),
+            _ => None,
+        }
+    }
+
+    /// Whether rodio's own decoder handles this format,
+    /// the others are decoded by `crate::decoder`
+    pub fn rodio_native(self) -> bool {
+        matches!(self, Self::Mp3 | Self::Wav | Self::Flac | Self::Vorbis)
+    }
+}
+
+/// Extensions of all supported formats, for file dialogs
+pub fn all_extensions() -> Vec<&'static str> {
+    AudioFormat::ALL
+        .into_iter()
+        .flat_map(|format| format.extensions().iter().copied())
+        .collect()
+}
+
+/// Detects the format of `path` by its content, the extension is only used
+/// if the content is ambiguous. Returns `None` for unsupported files
+pub fn probe(path: &Path) -> Option<AudioFormat> {
+    if !path.is_file() {
+        return None;
+    }
+    let probe = Probe::open(path).ok()?.guess_file_type().ok()?;
+    AudioFormat::from_file_type(probe.file_type()?)
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    probe(path).is_some()
+}
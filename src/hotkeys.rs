@@ -0,0 +1,156 @@
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+};
+
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use serde::{Deserialize, Serialize};
+
+use crate::audio_player::PlayerAction;
+use crate::audio_resource::AudioResource;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum HotkeyTarget {
+    Action(PlayerAction),
+    Pad(Box<AudioResource>),
+}
+
+impl HotkeyTarget {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Action(action) => format!("{:?}", action),
+            Self::Pad(resource) => match resource.path.file_stem() {
+                Some(stem) => format!("Pad: {}", stem.to_string_lossy()),
+                None => "Pad".to_string(),
+            },
+        }
+    }
+}
+
+/// A key chord like `ctrl+shift+F1` bound to a `HotkeyTarget`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub chord: String,
+    pub target: HotkeyTarget,
+}
+
+/// Parses `chord` and returns it in the form global-hotkey prints it,
+/// so that differently written chords for the same keys compare equal
+pub fn normalize_chord(chord: &str) -> Result<String, String> {
+    HotKey::from_str(chord)
+        .map(|hotkey| hotkey.into_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the index of the binding in `bindings` that already uses `chord`, skipping `except`
+pub fn find_conflict(bindings: &[Binding], chord: &str, except: Option<usize>) -> Option<usize> {
+    let chord = normalize_chord(chord).ok()?;
+    bindings
+        .iter()
+        .enumerate()
+        .find(|(i, b)| Some(*i) != except && normalize_chord(&b.chord).ok() == Some(chord.clone()))
+        .map(|(i, _)| i)
+}
+
+/// System wide hotkeys, these fire even while the window is not focused
+pub struct Hotkeys {
+    manager: Option<GlobalHotKeyManager>,
+    registered: Vec<(HotKey, HotkeyTarget)>,
+    receiver: Receiver<GlobalHotKeyEvent>,
+}
+
+impl Hotkeys {
+    /// `wake` is called from the hotkey thread on every event, use it to request a repaint
+    pub fn new(wake: impl Fn() + Send + Sync + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event| {
+            let _ = sender.send(event);
+            wake();
+        }));
+
+        Self {
+            manager: GlobalHotKeyManager::new().ok(),
+            registered: Vec::new(),
+            receiver,
+        }
+    }
+    pub fn available(&self) -> bool {
+        self.manager.is_some()
+    }
+    pub fn register(&mut self, bindings: &[Binding]) -> Vec<String> {
+        /*
+            Replace all registered hotkeys with `bindings`,
+            returns a message for every binding that couldn't be registered
+        */
+        let Some(manager) = &self.manager else {
+            return vec!["Global hotkeys are not supported on this system".to_string()];
+        };
+
+        for (hotkey, _) in self.registered.drain(..) {
+            let _ = manager.unregister(hotkey);
+        }
+
+        let mut errors = Vec::new();
+        for binding in bindings {
+            let hotkey = match HotKey::from_str(&binding.chord) {
+                Ok(hotkey) => hotkey,
+                Err(e) => {
+                    errors.push(format!("{}: {}", binding.chord, e));
+                    continue;
+                }
+            };
+            // Another application may already own the chord
+            if let Err(e) = manager.register(hotkey) {
+                errors.push(format!("{}: {}", binding.chord, e));
+                continue;
+            }
+            self.registered.push((hotkey, binding.target.clone()));
+        }
+        errors
+    }
+    pub fn poll(&self) -> Vec<HotkeyTarget> {
+        /*
+            Collect the targets of all hotkeys pressed since the last poll
+        */
+        self.receiver
+            .try_iter()
+            .filter(|event| event.state() == HotKeyState::Pressed)
+            .filter_map(|event| {
+                self.registered
+                    .iter()
+                    .find(|(hotkey, _)| hotkey.id() == event.id())
+                    .map(|(_, target)| target.clone())
+            })
+            .collect()
+    }
+}
+
+impl Debug for Hotkeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hotkeys")
+            .field("available", &self.available())
+            .field("registered", &self.registered)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_saved_before_pads_were_boxed_still_load() {
+        // Boxing is invisible to serde, pads are still stored as their resource
+        let json = r#"[{"chord":"ctrl+F1","target":{"Action":"Play"}},{"chord":"ctrl+F2","target":{"Pad":"/nonexistent/clip.mp3"}}]"#;
+        let bindings: Vec<Binding> = serde_json::from_str(json).unwrap();
+        assert_eq!(bindings[0].target, HotkeyTarget::Action(PlayerAction::Play));
+        match &bindings[1].target {
+            HotkeyTarget::Pad(resource) => {
+                assert_eq!(resource.path, std::path::Path::new("/nonexistent/clip.mp3"))
+            }
+            target => panic!("{:?}", target),
+        }
+        assert_eq!(serde_json::to_string(&bindings).unwrap(), json);
+    }
+}
@@ -1,10 +1,27 @@
 pub mod audio_player;
 pub mod audio_resource;
+pub mod cover_art;
+pub mod decoder;
+pub mod error;
+mod fade;
+pub mod formats;
+pub mod hotkeys;
+pub mod metadata;
 pub mod options;
+pub mod output_device;
+pub mod paths;
+pub mod play_queue;
+pub mod playlist;
+pub mod platform;
 pub mod platform_media_controls;
-pub mod winapi_;
+pub mod playback_position;
+pub mod soundboard;
+pub mod tagging;
 pub mod ytdlp;
 
 pub use audio_player::AudioPlayer;
 pub use audio_resource::AudioResource;
+pub use error::SoursError;
 pub use options::Options;
+pub use playback_position::PlaybackPosition;
+pub use soundboard::Soundboard;
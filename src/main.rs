@@ -1,13 +1,52 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use eframe::egui::{self, Color32};
-use sours::{winapi_, ytdlp, AudioResource};
-use sours::{AudioPlayer, Options};
-use std::time::Duration;
+use sours::audio_player::PlayerAction;
+use sours::formats;
+use sours::hotkeys::{self, Binding, HotkeyTarget, Hotkeys};
+use sours::metadata::Column;
+use sours::options::{PlaylistFormat, View, CURRENT_VERSION};
+use sours::output_device::{DeviceWatcher, Devices};
+use sours::paths::{self, Paths};
+use sours::platform::{self, Platform};
+use sours::platform_media_controls::MediaControls;
+use sours::play_queue::RepeatMode;
+use sours::playback_position::mmss;
+use sours::playlist::PlaylistId;
+use sours::soundboard::{OutputRoute, Pad};
+use sours::tagging;
+use sours::ytdlp::{
+    self, DownloadEvent, DownloadId, DownloadState, ExtractFormat, HistoryEntry, YtDlpSettings,
+};
+use sours::AudioResource;
+use sours::{AudioPlayer, Options, Soundboard};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 fn main() {
-    // Load options from JSON
-    let options = Options::load_from_json("sours.json");
+    // Find where files are kept, and move them there from the working directory
+    // older versions kept them in, unless an options file was given
+    let config = config_arg();
+    let migrate = config.is_none();
+    let paths = Paths::detect(config);
+    let mut setup = paths.create_dirs();
+    if migrate && setup.is_ok() {
+        setup = paths.migrate_from_working_dir().map(|_| ());
+    }
+    if let Err(e) = setup {
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title("sours")
+            .set_description(format!("Could not set up the directories of sours: {}", e))
+            .show();
+    }
+    paths::init(paths.clone());
+
+    // Load options from JSON, the user decides how to recover from broken ones
+    let Some(options) = load_options(&paths.config) else {
+        return;
+    };
 
     // Setup window options
     let native_options = eframe::NativeOptions {
@@ -16,7 +55,8 @@ fn main() {
             .with_resizable(true)
             .with_max_inner_size(egui::Vec2::new(800.0, 550.0))
             .with_min_inner_size(egui::Vec2::new(260.0, 200.0))
-            .with_maximize_button(false),
+            .with_maximize_button(false)
+            .with_window_level(window_level(options.always_on_top)),
         ..Default::default()
     };
 
@@ -28,36 +68,192 @@ fn main() {
     );
 }
 
+/// The options file given with `--config <path>` or `--config=<path>`
+fn config_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+fn load_options(path: &Path) -> Option<Options> {
+    /*
+        Ask whether to restore the last options that loaded or to reset to the defaults
+        as long as they can't be loaded, `None` if the user would rather quit
+    */
+    let mut result = Options::load_from_json(path);
+    loop {
+        let error = match result {
+            Ok(options) => return Some(options),
+            Err(error) => error,
+        };
+
+        let broken = Options::broken_path(path);
+        let mut description = format!(
+            "{} could not be loaded, it will be kept as {}.\n\n{}\n\n",
+            path.display(),
+            broken.display(),
+            error
+        );
+        let has_backup = Options::backup_path(path).exists();
+        let buttons = if has_backup {
+            description.push_str(
+                "Yes restores the last settings that loaded, No resets to the defaults, Cancel quits.",
+            );
+            rfd::MessageButtons::YesNoCancel
+        } else {
+            description.push_str("Ok resets to the defaults, Cancel quits.");
+            rfd::MessageButtons::OkCancel
+        };
+
+        let choice = rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("sours")
+            .set_description(description)
+            .set_buttons(buttons)
+            .show();
+        result = match choice {
+            rfd::MessageDialogResult::Yes => Options::restore_backup(path),
+            rfd::MessageDialogResult::No | rfd::MessageDialogResult::Ok => Options::reset(path),
+            _ => return None,
+        };
+    }
+}
+
+fn window_level(always_on_top: bool) -> egui::WindowLevel {
+    match always_on_top {
+        true => egui::WindowLevel::AlwaysOnTop,
+        false => egui::WindowLevel::Normal,
+    }
+}
+
 #[derive(Debug, Default)]
 struct State {
     yt_url: String,
     multiselect: Vec<AudioResource>,
     downloader: ytdlp::Downloader,
+    show_downloads: bool,
+    show_yt_dlp_settings: bool,
+    // Title cleaning rule being typed, and a title to try the rules on
+    title_rule: String,
+    title_test: String,
+    // The playlist each download is added to
+    download_targets: HashMap<DownloadId, PlaylistId>,
+    scrub: Option<f32>,
+    show_hotkeys: bool,
+    hotkey_target: Option<HotkeyTarget>,
+    rebinding: Option<Rebind>,
+    hotkey_conflict: Option<(String, usize)>,
+    hotkey_errors: Vec<String>,
+    device_error: Option<String>,
+    // The output devices as last reported by the watcher
+    devices: Devices,
+    route_errors: Vec<String>,
+    platform_error: Option<String>,
+    notifications: Vec<Notification>,
+    // Name typed for new and renamed playlists
+    playlist_name: String,
+    playlist_error: Option<String>,
+    // Why the options won't be saved on exit
+    save_error: Option<String>,
+}
+
+// A message in the notification area, failed downloads can be retried from there
+#[derive(Debug, Clone)]
+struct Notification {
+    text: String,
+    retry: Option<HistoryEntry>,
+    shown: Instant,
+}
+
+/// How long notifications that aren't failures are shown
+const NOTIFICATION_TIME: Duration = Duration::from_secs(5);
+
+// Which binding the next pressed chord is assigned to
+#[derive(Debug, Clone)]
+enum Rebind {
+    Existing(usize),
+    New(HotkeyTarget),
 }
 
 struct App {
     options: Options,
     player: AudioPlayer,
+    soundboard: Soundboard,
+    hotkeys: Hotkeys,
+    media_controls: MediaControls,
+    device_watcher: DeviceWatcher,
     state: State,
+    // Cover of the resource it was loaded for, `None` if that has no cover
+    cover: Option<(AudioResource, Option<egui::TextureHandle>)>,
 }
 
 impl App {
-    fn new(_cc: &eframe::CreationContext<'_>, options: Options) -> Self {
-        // Create A player for playback and timing
-        let mut player = AudioPlayer::default();
+    fn new(cc: &eframe::CreationContext<'_>, options: Options) -> Self {
+        // Create A player for playback and timing on the saved output device
+        let (mut player, device_result) = AudioPlayer::new(options.output_device.clone());
 
         // Change the players volume to the last saved volume
         player.set_volume_100(options.volume);
+        player.set_crossfade(Duration::from_secs(options.crossfade_secs.min(12) as u64));
+        player.set_fade_pause_stop(options.fade_pause_stop);
+
+        // Create the soundboard on the same output as the player
+        let mut soundboard = Soundboard::new(player.stream_handle().cloned());
+        soundboard.set_master_volume_100(options.volume);
+
+        // Also play pads to the extra outputs, e.g. a virtual microphone
+        let route_errors = soundboard.set_routes(&options.soundboard_routes);
+
+        // Register global hotkeys, wake the ui on every hotkey
+        // so they are handled even if the window is unfocused
+        let ctx = cc.egui_ctx.clone();
+        let mut hotkeys = Hotkeys::new(move || ctx.request_repaint());
+        let hotkey_errors = if options.hotkeys.is_empty() {
+            Vec::new()
+        } else {
+            hotkeys.register(&options.hotkeys)
+        };
 
-        // Set window to always be on top if configured
-        if options.always_on_top {
-            winapi_::set_window_always_on_top("sours", true);
-        }
+        // Publish now-playing info to the OS and receive its media keys,
+        // Windows needs the window for that
+        let hwnd = platform::native().window_handle("sours");
+        let ctx = cc.egui_ctx.clone();
+        let media_controls = MediaControls::new(hwnd, move || ctx.request_repaint());
+
+        // Notice output devices being plugged in or out, even while the ui is idle
+        let ctx = cc.egui_ctx.clone();
+        let device_watcher =
+            DeviceWatcher::new(Duration::from_secs(2), move || ctx.request_repaint());
+
+        let mut state = State {
+            hotkey_errors,
+            device_error: Self::device_error(&player, device_result),
+            route_errors,
+            save_error: (options.version > CURRENT_VERSION).then(|| {
+                "sours.json is from a newer version of sours, changes won't be saved".to_string()
+            }),
+            ..Default::default()
+        };
+
+        // Tell right away if downloads can't work, instead of once one fails
+        state.downloader.check_binary(options.yt_dlp.binary());
 
         Self {
             options,
             player,
-            state: State::default(),
+            soundboard,
+            hotkeys,
+            media_controls,
+            device_watcher,
+            state,
+            cover: None,
         }
     }
     // Display the playlist
@@ -69,14 +265,30 @@ impl App {
             scroll.show(ui, |ui| {
                 let playlist_layout = egui::Layout::top_down_justified(egui::Align::LEFT);
                 ui.with_layout(playlist_layout, |ui| {
-                    let playlist_slice = self.options.playlist.clone();
+                    let playlist_slice = self.options.playlist().resources.clone();
+
+                    // Fall back to the title if every column was hidden
+                    let columns = match self.options.playlist_columns.is_empty() {
+                        true => vec![Column::Title],
+                        false => self.options.playlist_columns.clone(),
+                    };
+
+                    // Column headers
+                    ui.horizontal(|ui| {
+                        for column in &columns {
+                            ui.add_sized(
+                                [column.width(), ui.spacing().interact_size.y],
+                                egui::Label::new(egui::RichText::new(column.name()).strong()),
+                            );
+                        }
+                    });
 
                     // Iterate through all saved `AudioResaource`'s
                     for (i, resource) in playlist_slice.iter().enumerate() {
                         let checked;
 
                         // Check if the resource is selected
-                        if let Some(selected) = &self.options.selected {
+                        if let Some(selected) = &self.options.playlist().selected {
                             if selected == resource {
                                 checked = true;
                             } else {
@@ -99,32 +311,53 @@ impl App {
                             }
                         }
 
-                        let audio_basename =
-                            String::from(resource.path.file_name().unwrap().to_string_lossy());
-
-                        // Use `RchText` to allow for red text if the resource is unavailable
-                        let mut display_text = egui::RichText::new(audio_basename);
-                        if !resource.playable() {
-                            display_text = display_text.color(Color32::RED);
-                        }
-
                         egui::Frame::default().fill(fill).show(ui, |ui| {
-                            // Add the `AudioResource` to the playlist display as `SelectableLabel`
-                            let re = ui.add(egui::SelectableLabel::new(
-                                checked && resource.playable(),
-                                display_text,
-                            ));
+                            // Add the `AudioResource` to the playlist display as a row of
+                            // `SelectableLabel`s, one per column, which act as one
+                            let mut re = ui
+                                .horizontal(|ui| {
+                                    let mut row: Option<egui::Response> = None;
+                                    for column in &columns {
+                                        let text = Self::fit_column(
+                                            resource.column_text(*column),
+                                            column.width(),
+                                        );
+
+                                        // Use `RchText` to allow for red text if the resource is unavailable
+                                        let mut text = egui::RichText::new(text);
+                                        if !resource.playable() {
+                                            text = text.color(Color32::RED);
+                                        }
+                                        let cell = ui.add_sized(
+                                            [column.width(), ui.spacing().interact_size.y],
+                                            egui::SelectableLabel::new(
+                                                checked && resource.playable(),
+                                                text,
+                                            ),
+                                        );
+                                        row = Some(match row {
+                                            Some(row) => row.union(cell),
+                                            None => cell,
+                                        });
+                                    }
+                                    // There's always at least one column
+                                    row.unwrap()
+                                })
+                                .inner;
+                            if let Some(error) = resource.error_message() {
+                                re = re.on_hover_text(error);
+                            }
 
                             // If the label is clicked and is playabel ->
                             // set the currently selected resource
                             if re.clicked() && resource.playable() {
-                                self.options.selected = Some(resource.clone());
+                                self.options.playlist_mut().selected = Some(resource.clone());
                             }
 
                             // If double clicked play currently selected resource
                             // Currently selected resource can logically only be the one doubleclicked
                             if re.double_clicked() && resource.playable() {
-                                self.player.play(resource.clone());
+                                self.play(resource.clone());
                             }
 
                             // Save the RightClickMenu's previous state
@@ -141,43 +374,51 @@ impl App {
 
                                     // Add play-button to ctxmenu if the resource is playable
                                     if ui.add_enabled(resource.playable(), play_button).clicked() {
-                                        self.options.selected = Some(resource.clone());
-                                        self.player.play(resource.clone());
+                                        self.options.playlist_mut().selected =
+                                            Some(resource.clone());
+                                        self.play(resource.clone());
+                                        show_ctx = false;
+                                        ui.close_menu();
+                                    }
+
+                                    // Queue the resource right after the playing one, or at the end
+                                    if ui
+                                        .add_enabled(
+                                            resource.playable(),
+                                            egui::Button::new("Play Next"),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.options.queue.play_next(resource.clone());
+                                        show_ctx = false;
+                                        ui.close_menu();
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            resource.playable(),
+                                            egui::Button::new("Add to Queue"),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.options.queue.add(resource.clone());
                                         show_ctx = false;
                                         ui.close_menu();
                                     }
 
-                                    // Add open-button that reveals the resource's file in the explorer
+                                    // Add open-button that reveals the resource's file in the file manager
                                     if ui
                                         .add_enabled(resource.playable(), egui::Button::new("Open"))
                                         .clicked()
                                     {
-                                        let path =
-                                            std::fs::canonicalize(&resource.path.parent().unwrap())
-                                                .unwrap();
-                                        let pathstr = path.to_str().unwrap().replace("\\\\?\\", "");
-
-                                        let _ = std::process::Command::new("explorer.exe")
-                                            .arg(&pathstr)
-                                            .output()
-                                            .unwrap();
+                                        let result = platform::native().reveal(&resource.path);
+                                        self.platform_result(result);
                                     }
                                     // Add remove-button that removes the resource from the playlist
                                     if ui
                                         .button(egui::RichText::new("Remove").color(Color32::RED))
                                         .clicked()
                                     {
-                                        self.options.remove_resource(resource);
-                                        if let Some(selected) = &self.options.selected {
-                                            if selected == resource {
-                                                self.options.selected = None;
-                                            }
-                                        }
-                                        if let Some(current) = &self.player.current {
-                                            if current == resource {
-                                                self.player.stop();
-                                            }
-                                        }
+                                        self.remove_resource(resource);
                                         show_ctx = false;
                                         ui.close_menu();
                                     }
@@ -187,21 +428,11 @@ impl App {
                             // todo: Change to make `Del`, delete currently selected
                             // If `Del` is pressed delete all multiselected resources
                             if ui.ctx().input(|i| i.key_pressed(egui::Key::Delete)) {
-                                if self.options.selected.is_some()
+                                if self.options.playlist().selected.is_some()
                                     && !self.state.multiselect.is_empty()
                                 {
-                                    for res in &self.state.multiselect {
-                                        self.options.remove_resource(res);
-                                        if let Some(selected) = &self.options.selected {
-                                            if selected == resource {
-                                                self.options.selected = None;
-                                            }
-                                        }
-                                        if let Some(current) = &self.player.current {
-                                            if current == resource {
-                                                self.player.stop();
-                                            }
-                                        }
+                                    for res in self.state.multiselect.clone() {
+                                        self.remove_resource(&res);
                                     }
                                 }
                             }
@@ -211,17 +442,127 @@ impl App {
             });
         });
     }
-    fn open_sours_json(&self) {
+    fn fit_column(text: String, width: f32) -> String {
         /*
-           Superflous function for opening `sours.json`
-           in window's default app for JSON files
+            Cut `text` off with an ellipsis so it roughly fits a column `width` pixels wide
         */
+        let max_chars = (width / 7.0) as usize;
+        if text.chars().count() <= max_chars {
+            return text;
+        }
+        let mut text: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+        text.push('…');
+        text
+    }
+    // Display the soundboard's pads as a grid
+    fn pads(&mut self, ui: &mut egui::Ui) {
+        const PAD_SIZE: egui::Vec2 = egui::Vec2::new(80.0, 56.0);
+
+        let frame = egui::Frame::default().fill(Color32::from_rgb(35, 35, 35));
+        let scroll = egui::ScrollArea::vertical().max_height(ui.available_height());
+
+        // While clips are playing keep repainting to update the pad highlights
+        if self.soundboard.playing_count() > 0 {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+
+        frame.show(ui, |ui| {
+            scroll.show(ui, |ui| {
+                if self.options.pads.is_empty() {
+                    ui.label("No pads yet, add some from the Soundboard menu");
+                    return;
+                }
+
+                // Fit as many pads into a row as the window allows
+                let spacing = ui.spacing().item_spacing.x;
+                let columns = ((ui.available_width() + spacing) / (PAD_SIZE.x + spacing)) as usize;
+                let columns = columns.max(1);
+
+                let pads = self.options.pads.clone();
+                egui::Grid::new("pads").show(ui, |ui| {
+                    for (i, pad) in pads.iter().enumerate() {
+                        let playing = self.soundboard.is_playing(&pad.resource);
+
+                        // Use `RchText` to allow for red text if the resource is unavailable
+                        let mut display_text = egui::RichText::new(pad.name());
+                        if !pad.resource.playable() {
+                            display_text = display_text.color(Color32::RED);
+                        }
+
+                        let mut button = egui::Button::new(display_text)
+                            .wrap(true)
+                            .min_size(PAD_SIZE);
+                        if playing {
+                            button = button.fill(Color32::from_rgb(50, 55, 77));
+                        }
+
+                        let hover = match pad.resource.error_message() {
+                            Some(error) => error,
+                            None => pad.resource.path.to_string_lossy().to_string(),
+                        };
+                        let re = ui
+                            .add_enabled(pad.resource.playable(), button)
+                            .on_disabled_hover_text(&hover)
+                            .on_hover_text(hover);
+
+                        // Clicking a pad starts the clip on top of whatever is playing,
+                        // clicking it with the middle mouse button stops it
+                        if re.clicked() {
+                            self.play_pad(pad);
+                        }
+                        if re.clicked_by(egui::PointerButton::Middle) {
+                            self.soundboard.stop(&pad.resource);
+                        }
+
+                        re.context_menu(|ui| {
+                            // Per pad volume
+                            let mut volume = pad.volume;
+                            ui.label("Volume");
+                            let slider = egui::Slider::new(&mut volume, 0..=100)
+                                .trailing_fill(true)
+                                .show_value(true);
+                            if ui.add(slider).changed() {
+                                if let Some(p) = self.options.pads.get_mut(i) {
+                                    p.volume = volume;
+                                    self.soundboard.set_pad_volume(p);
+                                }
+                            }
+
+                            if ui.add_enabled(playing, egui::Button::new("Stop")).clicked() {
+                                self.soundboard.stop(&pad.resource);
+                                ui.close_menu();
+                            }
+
+                            // Add remove-button that removes the pad from the soundboard
+                            if ui
+                                .button(egui::RichText::new("Remove").color(Color32::RED))
+                                .clicked()
+                            {
+                                self.soundboard.stop(&pad.resource);
+                                self.options.remove_pad(&pad.resource);
+                                ui.close_menu();
+                            }
+                        });
 
-        let cwd = std::env::current_dir().unwrap();
-        let json_path = cwd.join(std::path::Path::new("sours.json"));
-        let pathstr = json_path.to_str().unwrap();
+                        if (i + 1) % columns == 0 {
+                            ui.end_row();
+                        }
+                    }
+                });
+            });
+        });
+    }
+    fn open_sours_json(&mut self) {
+        /*
+           Superflous function for opening `sours.json`
+           in the system's default app for JSON files
+        */
 
-        winapi_::open_file_in_default_application(pathstr);
+        let result = platform::native().open(&paths::get().config);
+        self.platform_result(result);
+    }
+    fn platform_result(&mut self, result: std::io::Result<()>) {
+        self.state.platform_error = result.err().map(|e| format!("Could not open: {}", e));
     }
     fn _handle_multiselect(&mut self) {}
     fn _set_selected(&mut self) {}
@@ -231,14 +572,67 @@ impl App {
            add chosen files to playlist
         */
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("audio", &["wav", "mp3"])
+            .add_filter("audio", &formats::all_extensions())
             .pick_file()
         {
-            if !self.options.playlist.iter().any(|x| x.path == path) {
+            if !self
+                .options
+                .playlist()
+                .resources
+                .iter()
+                .any(|x| x.path == path)
+            {
                 self.options.add_resource(path);
             }
         }
     }
+    fn playlist_file_dialog(&self) -> rfd::FileDialog {
+        let mut dialog = rfd::FileDialog::new();
+        for format in PlaylistFormat::ALL {
+            dialog = dialog.add_filter(format.name(), format.extensions());
+        }
+        dialog
+    }
+    fn ask_import_playlist(&mut self) {
+        /*
+            Open a file-dialog and ask to select a playlist file,
+            add it as a new playlist
+        */
+        if let Some(path) = self.playlist_file_dialog().pick_file() {
+            let result = self.options.import_playlist(&path);
+            self.state.playlist_error = result.err().map(|e| e.to_string());
+            self.state.multiselect.clear();
+        }
+    }
+    fn ask_export_playlist(&mut self) {
+        /*
+            Open a file-dialog and ask where to save the active playlist,
+            the chosen extension decides the format
+        */
+        let file_name = format!("{}.m3u8", self.options.playlist().name);
+        if let Some(path) = self
+            .playlist_file_dialog()
+            .set_file_name(file_name)
+            .save_file()
+        {
+            let result = self.options.export_playlist(&path);
+            self.state.playlist_error = result.err().map(|e| e.to_string());
+        }
+    }
+    fn ask_add_pads(&mut self) {
+        /*
+           Open a file-dialog and ask to select audio files
+           add chosen files as soundboard pads
+        */
+        if let Some(paths) = rfd::FileDialog::new()
+            .add_filter("audio", &formats::all_extensions())
+            .pick_files()
+        {
+            for path in paths {
+                self.options.add_pad(path);
+            }
+        }
+    }
     fn consume_keyboard_shortcuts(&mut self, ui: &mut egui::Ui) {
         /*
             Initiate `ctrl + o` as keyboard shortcut to open files
@@ -276,25 +670,29 @@ impl App {
 
                     // Youtube download menu
 
-                    ui.add_enabled_ui(self.state.downloader.is_finished(), |ui| {
-                        ui.menu_button("From Youtube", |ui| {
-                            // `n` maximum playlist downloads
-                            let n = 10;
-                            ui.label("Enter Youtube URL:");
-                            let entry = ui
-                                .text_edit_singleline(&mut self.state.yt_url)
-                                .on_hover_text("Enter Youtube URL");
-                            let button_download = ui.button("Download");
-
-                            // If `Enter` or the download button were pressed, downloda the video
-                            if (entry.lost_focus() || button_download.clicked())
-                                && !self.state.yt_url.is_empty()
-                            {
-                                self.state.downloader.download(self.state.yt_url.clone(), n);
-                                self.state.yt_url.clear();
-                            }
-                        });
+                    ui.menu_button("From Youtube", |ui| {
+                        ui.label("Enter Youtube URL:");
+                        let entry = ui
+                            .text_edit_singleline(&mut self.state.yt_url)
+                            .on_hover_text("Enter Youtube URL");
+                        let button_download = ui.button("Download");
+
+                        // If `Enter` or the download button were pressed, queue the download
+                        if (entry.lost_focus() || button_download.clicked())
+                            && !self.state.yt_url.is_empty()
+                        {
+                            self.queue_download(self.state.yt_url.clone(), None);
+                            self.state.yt_url.clear();
+                        }
+                        if ui.button("Settings…").clicked() {
+                            self.state.show_yt_dlp_settings = true;
+                            ui.close_menu();
+                        }
                     });
+                    if ui.button("Downloads").clicked() {
+                        self.state.show_downloads = true;
+                        ui.close_menu();
+                    }
 
                     ui.separator();
                 });
@@ -308,8 +706,8 @@ impl App {
                     // Show play-button
                     if ui.add_enabled(!self.player.is_playing(), play).clicked() {
                         // Play selected if Some
-                        if let Some(resource) = self.options.selected.clone() {
-                            self.player.play(resource);
+                        if let Some(resource) = self.options.playlist().selected.clone() {
+                            self.play(resource);
                         }
                     }
                     // Pause-button
@@ -324,85 +722,121 @@ impl App {
 
                     // Autoplay checkbox
                     ui.checkbox(&mut self.options.autoplay, "Autoplay");
-                });
 
-                // Playlist menu category
-                ui.menu_button("Playlist", |ui| {
-                    // If playlist is not empty show shuffle button
+                    // Shuffle the play order, the playlist keeps its order
+                    let mut shuffle = self.options.queue.shuffled();
+                    if ui.checkbox(&mut shuffle, "🔀 Shuffle").changed() {
+                        self.options.queue.set_shuffle(shuffle);
+                    }
+
+                    // Repeat mode
+                    let mut repeat = self.options.queue.repeat();
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut repeat, RepeatMode::Off, "No Repeat");
+                        ui.radio_value(&mut repeat, RepeatMode::All, "🔁 All");
+                        ui.radio_value(&mut repeat, RepeatMode::One, "🔂 One");
+                    });
+                    self.options.queue.set_repeat(repeat);
+
+                    // Crossfade between tracks, 0 plays them gapless
+                    let crossfade = egui::Slider::new(&mut self.options.crossfade_secs, 0..=12)
+                        .text("Crossfade")
+                        .suffix("s");
+                    if ui.add(crossfade).changed() {
+                        let secs = self.options.crossfade_secs as u64;
+                        self.player.set_crossfade(Duration::from_secs(secs));
+                    }
                     if ui
-                        .add_enabled(
-                            !self.options.playlist.is_empty(),
-                            egui::Button::new("🔀 Shuffle"),
-                        )
-                        .clicked()
+                        .checkbox(&mut self.options.fade_pause_stop, "Fade on Pause/Stop")
+                        .changed()
                     {
-                        // Shuffle playlist
-                        self.options.shuffle();
+                        self.player
+                            .set_fade_pause_stop(self.options.fade_pause_stop);
+                    }
+                    ui.separator();
+
+                    // Open the hotkey bindings window
+                    if ui.button("Hotkeys").clicked() {
+                        self.state.show_hotkeys = true;
+                        ui.close_menu();
                     }
+                });
+
+                // Playlist menu category
+                ui.menu_button("Playlist", |ui| {
                     // If something is selected show remove button
                     if ui
                         .add_enabled(
-                            self.options.selected.is_some(),
+                            self.options.playlist().selected.is_some(),
                             egui::Button::new("Remove Selected"),
                         )
                         .clicked()
                     {
                         // Remove selected
-                        self.options
-                            .playlist
-                            .retain(|r| r != self.options.selected.as_ref().unwrap());
-
-                        if self.options.selected == self.player.current {
-                            self.player.stop();
+                        if let Some(selected) = self.options.playlist().selected.clone() {
+                            self.remove_resource(&selected);
                         }
 
                         /*
                             If another resource was playing, set the next selected
                             resource after the removed one to the playing one
                         */
-                        self.options.selected = self.player.current.clone();
+                        self.options.playlist_mut().selected = self.player.current.clone();
                     }
 
                     if ui
                         .add_enabled(
-                            self.options.selected.is_some(),
+                            self.options.playlist().selected.is_some(),
                             egui::Button::new("Move Up"),
                         )
                         .clicked()
                     {
                         // move the selected resource up
-                        let index = self
-                            .options
-                            .playlist
+                        let playlist = self.options.playlist_mut();
+                        let index = playlist
+                            .resources
                             .iter()
-                            .position(|r| r == self.options.selected.as_ref().unwrap())
+                            .position(|r| Some(r) == playlist.selected.as_ref())
                             .unwrap();
 
                         let new_index = if index > 0 {
                             index - 1
                         } else {
-                            (index + self.options.playlist.len() - 1) % self.options.playlist.len()
+                            (index + playlist.resources.len() - 1) % playlist.resources.len()
                         };
-                        self.options.playlist.swap(index, new_index as usize);
+                        playlist.resources.swap(index, new_index as usize);
                     }
                     if ui
                         .add_enabled(
-                            self.options.selected.is_some(),
+                            self.options.playlist().selected.is_some(),
                             egui::Button::new("Move Down"),
                         )
                         .clicked()
                     {
                         // move the selected resource down
-                        let index = self
-                            .options
-                            .playlist
+                        let playlist = self.options.playlist_mut();
+                        let index = playlist
+                            .resources
                             .iter()
-                            .position(|r| r == self.options.selected.as_ref().unwrap())
+                            .position(|r| Some(r) == playlist.selected.as_ref())
                             .unwrap();
 
-                        let new_index = (index + 1) % self.options.playlist.len();
-                        self.options.playlist.swap(index, new_index);
+                        let new_index = (index + 1) % playlist.resources.len();
+                        playlist.resources.swap(index, new_index);
                     }
+                    ui.separator();
+
+                    // Import from and export to other players' playlist files
+                    if ui.button("Import playlist…").clicked() {
+                        self.ask_import_playlist();
+                        ui.close_menu();
+                    }
+                    if ui.button("Export playlist…").clicked() {
+                        self.ask_export_playlist();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+
                     // Clear button to clear the whole playlist
                     // todo: prompt the user to confirm deletion
                     if ui
@@ -411,9 +845,87 @@ impl App {
                         ))
                         .clicked()
                     {
-                        self.options.playlist_clear();
+                        if let Err(e) = self.options.playlist_clear() {
+                            self.state.playlist_error = Some(format!("Could not delete: {}", e));
+                        }
                         self.player.stop();
-                        self.options.selected = None;
+                        self.options.playlist_mut().selected = None;
+                    }
+                });
+
+                // Playlist switcher, titled with the active playlist
+                let active = format!("📃 {}", self.options.playlist().name);
+                ui.menu_button(active, |ui| {
+                    self.playlists_menu(ui);
+                });
+
+                // Soundboard menu category
+                ui.menu_button("Soundboard", |ui| {
+                    if ui.button("Add Pads").clicked() {
+                        self.ask_add_pads();
+                        self.options.view = View::Soundboard;
+                        ui.close_menu();
+                    }
+                    // Stop every clip that is currently playing
+                    if ui
+                        .add_enabled(
+                            self.soundboard.playing_count() > 0,
+                            egui::Button::new("Stop All"),
+                        )
+                        .clicked()
+                    {
+                        self.soundboard.stop_all();
+                    }
+                });
+
+                // View menu category, switch between playlist and soundboard
+                ui.menu_button("View", |ui| {
+                    ui.radio_value(&mut self.options.view, View::Playlist, "Playlist");
+                    ui.radio_value(&mut self.options.view, View::Soundboard, "Soundboard");
+                    ui.separator();
+                    ui.checkbox(&mut self.options.show_now_playing, "Now Playing");
+                    ui.checkbox(&mut self.options.show_queue, "Queue");
+
+                    // Toggle the playlist's columns, new ones are added at the end
+                    ui.menu_button("Columns", |ui| {
+                        for column in Column::ALL {
+                            let mut shown = self.options.playlist_columns.contains(&column);
+                            if ui.checkbox(&mut shown, column.name()).changed() {
+                                if shown {
+                                    self.options.playlist_columns.push(column);
+                                } else {
+                                    self.options.playlist_columns.retain(|c| *c != column);
+                                }
+                            }
+                        }
+                    });
+                });
+
+                // Settings menu category
+                ui.menu_button("Settings", |ui| {
+                    ui.menu_button("Output Device", |ui| {
+                        let before = self.options.output_device.clone();
+
+                        ui.radio_value(&mut self.options.output_device, None, "System Default");
+                        for name in self.state.devices.names.clone() {
+                            ui.radio_value(
+                                &mut self.options.output_device,
+                                Some(name.clone()),
+                                name,
+                            );
+                        }
+
+                        if before != self.options.output_device {
+                            self.switch_output_device();
+                        }
+                    });
+
+                    ui.menu_button("Soundboard Outputs", |ui| {
+                        self.soundboard_outputs(ui);
+                    });
+
+                    if let Some(error) = &self.state.device_error {
+                        ui.colored_label(Color32::RED, error);
                     }
                 });
 
@@ -424,10 +936,9 @@ impl App {
                     ui.checkbox(&mut self.options.show_debug, "Debug Menu");
 
                     if before_aot != self.options.always_on_top {
-                        sours::winapi_::set_window_always_on_top(
-                            "sours",
+                        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(window_level(
                             self.options.always_on_top,
-                        );
+                        )));
                     }
 
                     if ui.button("Open sours.json").clicked() {
@@ -435,79 +946,816 @@ impl App {
                     }
 
                     if ui.button("Select none").clicked() {
-                        self.options.selected = None;
+                        self.options.playlist_mut().selected = None;
                     }
                     ui.separator();
 
                     ui.menu_button("Info", |ui| ui.label("Version"));
                 });
 
+                // Audio device warning in the topmenubar
+                if let Some(error) = &self.state.device_error {
+                    ui.colored_label(Color32::RED, "⚠").on_hover_text(error);
+                }
+
+                // Failed open / reveal warning in the topmenubar
+                if let Some(error) = &self.state.platform_error {
+                    ui.colored_label(Color32::RED, "⚠").on_hover_text(error);
+                }
+
+                // Failed playlist import / export warning in the topmenubar
+                if let Some(error) = &self.state.playlist_error {
+                    ui.colored_label(Color32::RED, "⚠").on_hover_text(error);
+                }
+
+                // Options that can't be saved warning in the topmenubar
+                if let Some(error) = &self.state.save_error {
+                    ui.colored_label(Color32::RED, "⚠").on_hover_text(error);
+                }
+
                 // Youtube download indicator in the topmenubar
-                if !self.state.downloader.is_finished() {
+                if self.state.downloader.is_active() {
                     ui.monospace("Downloading ");
                     ui.spinner();
                 }
             })
         });
     }
-    fn debug_window(&mut self, ctx: &egui::Context) {
+    fn playlists_menu(&mut self, ui: &mut egui::Ui) {
         /*
-            Floating window displaying information about the app's state
+            Switch between the named playlists, and create, rename, duplicate or delete them
         */
-        if self.options.show_debug {
-            egui::Window::new("Debug Menu").show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Selected: ");
-                    let index = if let Some(selected) = self.options.selected.as_ref() {
-                        self.options
-                            .playlist
-                            .iter()
-                            .position(|r| r == selected)
-                            .unwrap()
-                    } else {
-                        0
-                    };
+        for index in 0..self.options.playlists.len() {
+            let active = index == self.options.active_playlist;
+            let name = self.options.playlists[index].name.clone();
+            if ui.radio(active, name).clicked() {
+                self.options.switch_playlist(index);
+                self.state.multiselect.clear();
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+
+        // Name for the new or renamed playlist
+        ui.text_edit_singleline(&mut self.state.playlist_name)
+            .on_hover_text("Playlist name");
+        let name = self.state.playlist_name.trim().to_string();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("New"))
+                .clicked()
+            {
+                self.options.create_playlist(&name);
+                self.state.playlist_name.clear();
+                self.state.multiselect.clear();
+                ui.close_menu();
+            }
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Rename"))
+                .clicked()
+            {
+                self.options
+                    .rename_playlist(self.options.active_playlist, &name);
+                self.state.playlist_name.clear();
+                ui.close_menu();
+            }
+        });
+        if ui.button("Duplicate").clicked() {
+            self.options
+                .duplicate_playlist(self.options.active_playlist);
+            self.state.multiselect.clear();
+            ui.close_menu();
+        }
 
-                    ui.label(format!("{:?}", index));
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Playing: ");
-                    let mut text = "None";
-                    if let Some(resource) = &self.player.current {
-                        text = resource.path.file_name().unwrap().to_str().unwrap();
-                    }
-                    ui.label(format!("{:?}", text));
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Volume: ");
-                    ui.label(format!("{:?}", self.options.volume));
-                });
-                ui.horizontal(|ui| {
-                    ui.label("UI Size: ");
-                    ui.label(format!("{:?}", self.options.ui_size));
-                });
-            });
+        // The last playlist can't be deleted
+        let delete = egui::Button::new(egui::RichText::new("Delete").color(Color32::RED));
+        if ui
+            .add_enabled(self.options.playlists.len() > 1, delete)
+            .clicked()
+        {
+            self.options.delete_playlist(self.options.active_playlist);
+            self.state.multiselect.clear();
+            ui.close_menu();
         }
     }
-    fn controls(&mut self, ui: &mut egui::Ui) {
+    fn downloads_window(&mut self, ctx: &egui::Context) {
         /*
-            Media controls like Play/Pause/Stop
+            Floating window with the progress of the running download,
+            and the queued and ended ones
         */
-        let frame = egui::Frame::default().fill(Color32::from_rgb(35, 35, 35));
+        let mut open = self.state.show_downloads;
+        egui::Window::new("Downloads")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                let downloads = self.state.downloader.downloads().to_vec();
+                if downloads.is_empty() {
+                    ui.weak("Nothing downloaded yet");
+                }
 
-        // Controls are only active if a resource is selected to act upon
-        ui.add_enabled_ui(self.options.selected.is_some(), |ui| {
-            frame.show(ui, |ui| {
-                ui.horizontal(|ui| {
-                    if self.player.is_playing() {
-                        // Pause button
-                        if ui.button("⏸").clicked() {
-                            self.player.pause();
+                egui::ScrollArea::vertical()
+                    .id_source("downloads")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for download in &downloads {
+                            let progress = &download.progress;
+                            ui.horizontal(|ui| {
+                                if download.state.is_pending() && ui.small_button("✖").clicked() {
+                                    self.state.downloader.cancel(download.id);
+                                }
+                                let title = progress.title.as_ref().unwrap_or(&download.url);
+                                ui.label(Self::fit_column(title.clone(), 220.0))
+                                    .on_hover_text(&download.url);
+                            });
+
+                            match &download.state {
+                                DownloadState::Queued => {
+                                    ui.weak("Queued");
+                                }
+                                DownloadState::Running => {
+                                    // Percent, speed, time left and position in the playlist
+                                    let mut text = match progress.percent {
+                                        Some(percent) => format!("{:.0}%", percent),
+                                        None => "Starting".to_string(),
+                                    };
+                                    if let Some(speed) = progress.speed {
+                                        text.push_str(&format!(
+                                            "  {}/s",
+                                            Self::format_bytes(speed)
+                                        ));
+                                    }
+                                    if let Some(eta) = progress.eta {
+                                        text.push_str(&format!("  {} left", mmss(eta)));
+                                    }
+                                    if let Some((item, count)) = progress.item {
+                                        text.push_str(&format!("  ({}/{})", item, count));
+                                    }
+                                    let fraction = progress.percent.unwrap_or(0.0) / 100.0;
+                                    ui.add(egui::ProgressBar::new(fraction).text(text));
+                                }
+                                DownloadState::Finished => {
+                                    ui.weak("Finished");
+                                }
+                                DownloadState::Failed(error) => {
+                                    ui.colored_label(Color32::RED, error.to_string());
+                                }
+                                DownloadState::Cancelled => {
+                                    ui.weak("Cancelled");
+                                }
+                            }
+                            ui.separator();
+                        }
+                    });
+
+                let ended = downloads.iter().any(|d| !d.state.is_pending());
+                if ui
+                    .add_enabled(ended, egui::Button::new("Clear Ended"))
+                    .clicked()
+                {
+                    self.state.downloader.clear_ended();
+                }
+
+                // Ended downloads of earlier sessions too, most recent first
+                egui::CollapsingHeader::new("History").show(ui, |ui| {
+                    let history = self.options.download_history.clone();
+                    egui::ScrollArea::vertical()
+                        .id_source("download_history")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for entry in history.iter().rev() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("⟳").on_hover_text("Retry").clicked() {
+                                        self.retry_download(entry);
+                                    }
+                                    let title = entry.title.as_ref().unwrap_or(&entry.url);
+                                    let label = ui
+                                        .label(Self::fit_column(title.clone(), 200.0))
+                                        .on_hover_text(&entry.url);
+                                    match &entry.state {
+                                        DownloadState::Failed(error) => {
+                                            label.on_hover_text(error.to_string());
+                                            ui.colored_label(Color32::RED, "Failed");
+                                        }
+                                        DownloadState::Cancelled => {
+                                            ui.weak("Cancelled");
+                                        }
+                                        _ => {
+                                            ui.weak("Finished");
+                                        }
+                                    }
+                                });
+                            }
+                        });
+                    if ui
+                        .add_enabled(!history.is_empty(), egui::Button::new("Clear History"))
+                        .clicked()
+                    {
+                        self.options.download_history.clear();
+                    }
+                });
+            });
+        self.state.show_downloads = open;
+    }
+    fn yt_dlp_window(&mut self, ctx: &egui::Context) {
+        /*
+            Floating window with how yt-dlp is run, changes apply to downloads queued after them
+        */
+        let mut open = self.state.show_yt_dlp_settings;
+        egui::Window::new("yt-dlp Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let settings = &mut self.options.yt_dlp;
+
+                egui::Grid::new("yt_dlp_settings")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Executable");
+                        ui.text_edit_singleline(&mut settings.binary).on_hover_text(
+                            "Path or name of yt-dlp, names are looked up on the PATH.\n\
+                         Empty for the one shipped with sours",
+                        );
+                        ui.end_row();
+
+                        ui.label("Format");
+                        egui::ComboBox::from_id_source("yt_dlp_format")
+                            .selected_text(settings.format.name())
+                            .show_ui(ui, |ui| {
+                                for format in ExtractFormat::ALL {
+                                    ui.selectable_value(
+                                        &mut settings.format,
+                                        format,
+                                        format.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        // Lossless formats have no bitrate to choose
+                        ui.label("Bitrate");
+                        let bitrate_text = |bitrate: Option<u32>| match bitrate {
+                            Some(kbps) => format!("{} kbit/s", kbps),
+                            None => "Best".to_string(),
+                        };
+                        ui.add_enabled_ui(settings.format.is_lossy(), |ui| {
+                            egui::ComboBox::from_id_source("yt_dlp_bitrate")
+                                .selected_text(bitrate_text(settings.bitrate))
+                                .show_ui(ui, |ui| {
+                                    for bitrate in
+                                        [None, Some(320), Some(256), Some(192), Some(128)]
+                                    {
+                                        let text = bitrate_text(bitrate);
+                                        ui.selectable_value(&mut settings.bitrate, bitrate, text);
+                                    }
+                                });
+                        });
+                        ui.end_row();
+
+                        ui.label("Playlist videos");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut settings.playlist_start)
+                                    .clamp_range(1..=9999),
+                            );
+                            let mut limited = settings.playlist_end.is_some();
+                            ui.checkbox(&mut limited, "to");
+                            let mut end =
+                                settings.playlist_end.unwrap_or(settings.playlist_start + 9);
+                            ui.add_enabled(
+                                limited,
+                                egui::DragValue::new(&mut end)
+                                    .clamp_range(settings.playlist_start..=9999),
+                            );
+                            settings.playlist_end =
+                                limited.then_some(end.max(settings.playlist_start));
+                        });
+                        ui.end_row();
+
+                        ui.label("File names");
+                        ui.text_edit_singleline(&mut settings.output_template)
+                            .on_hover_text(
+                                "yt-dlp output template, inside the downloads directory",
+                            );
+                        ui.end_row();
+
+                        ui.label("Cookies");
+                        ui.horizontal(|ui| {
+                            let cookies = settings.cookies.as_ref().map(|path| {
+                                Self::fit_column(path.to_string_lossy().to_string(), 150.0)
+                            });
+                            ui.label(cookies.unwrap_or_else(|| "None".to_string()));
+                            if ui.small_button("Browse…").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Cookies", &["txt"])
+                                    .pick_file()
+                                {
+                                    settings.cookies = Some(path);
+                                }
+                            }
+                            if settings.cookies.is_some() && ui.small_button("✖").clicked() {
+                                settings.cookies = None;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Rate limit");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.rate_limit)
+                                .hint_text("e.g. 500K"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Extra arguments");
+                        ui.text_edit_singleline(&mut settings.extra_args)
+                            .on_hover_text(
+                                "Passed to yt-dlp as they are, quote arguments with spaces",
+                            );
+                        ui.end_row();
+                    });
+
+                // What's written into the downloaded files
+                egui::CollapsingHeader::new("Tags").show(ui, |ui| {
+                    let tags = &mut self.options.yt_dlp.tags;
+                    ui.checkbox(&mut tags.enabled, "Tag downloaded files");
+                    ui.add_enabled_ui(tags.enabled, |ui| {
+                        ui.checkbox(&mut tags.embed_thumbnail, "Embed the thumbnail as cover");
+                        ui.checkbox(&mut tags.split_artist, "Artist from \"Artist - Title\"");
+
+                        ui.label("Remove from titles in () or [], * matches anything:");
+                        let mut remove = None;
+                        for (i, rule) in tags.title_rules.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").clicked() {
+                                    remove = Some(i);
+                                }
+                                ui.monospace(rule);
+                            });
+                        }
+                        if let Some(i) = remove {
+                            tags.title_rules.remove(i);
+                        }
+                        ui.horizontal(|ui| {
+                            let entry = ui.text_edit_singleline(&mut self.state.title_rule);
+                            let add = ui.button("Add").clicked()
+                                || entry.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            if add && !self.state.title_rule.trim().is_empty() {
+                                tags.title_rules
+                                    .push(self.state.title_rule.trim().to_string());
+                                self.state.title_rule.clear();
+                            }
+                        });
+
+                        // Try the rules on a title
+                        ui.horizontal(|ui| {
+                            ui.label("Test:");
+                            ui.text_edit_singleline(&mut self.state.title_test);
+                        });
+                        if !self.state.title_test.is_empty() {
+                            ui.weak(tagging::clean_title(
+                                &self.state.title_test,
+                                &tags.title_rules,
+                            ));
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    match self.state.downloader.version() {
+                        _ if self.state.downloader.is_checking() => {
+                            ui.spinner();
+                        }
+                        Some(Ok(version)) => {
+                            ui.label(format!("yt-dlp {}", version));
+                        }
+                        Some(Err(error)) => {
+                            ui.colored_label(Color32::RED, error.to_string());
+                        }
+                        None => {}
+                    }
+                    if ui.button("Check").clicked() {
+                        let binary = self.options.yt_dlp.binary();
+                        self.state.downloader.check_binary(binary);
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.options.yt_dlp = YtDlpSettings::default();
+                    }
+                });
+            });
+        self.state.show_yt_dlp_settings = open;
+    }
+    fn queue_download(&mut self, url: String, retry: Option<&HistoryEntry>) {
+        /*
+            Download into the playlist that is active now, even if another one is by the time it's done.
+            Retries download as much of a playlist as the first try did
+        */
+        let mut settings = self.options.yt_dlp.clone();
+        if let Some(entry) = retry {
+            settings.playlist_end = entry.playlist_end;
+        }
+        let id = self.state.downloader.download(url, settings);
+        let target = self.options.playlist().id;
+        self.state.download_targets.insert(id, target);
+        self.state.show_downloads = true;
+    }
+    fn retry_download(&mut self, entry: &HistoryEntry) {
+        self.queue_download(entry.url.clone(), Some(entry));
+    }
+    fn handle_downloads(&mut self, ctx: &egui::Context) {
+        /*
+            Add the files of finished downloads to the playlist, keep every ended download
+            in the history and tell about it in the notification area
+        */
+        let checking = self.state.downloader.is_checking();
+        let events = self.state.downloader.poll();
+        if checking && !self.state.downloader.is_checking() {
+            if let Some(Err(error)) = self.state.downloader.version() {
+                self.notify(format!("yt-dlp can't be used: {}", error), None);
+            }
+        }
+
+        for event in events {
+            let id = match event {
+                DownloadEvent::Progress(..) => continue,
+                DownloadEvent::TagFailed(_, path, error) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    self.notify(format!("{}\n{}", name, error), None);
+                    continue;
+                }
+                DownloadEvent::File(id, file) => {
+                    // Fall back to the active playlist if the target was deleted
+                    let index = self
+                        .state
+                        .download_targets
+                        .get(&id)
+                        .and_then(|target| self.options.playlist_index(*target))
+                        .unwrap_or(self.options.active_playlist);
+                    self.options.add_downloaded(&file, index);
+                    continue;
+                }
+                DownloadEvent::Finished(id)
+                | DownloadEvent::Failed(id, _)
+                | DownloadEvent::Cancelled(id) => id,
+            };
+            let Some(download) = self.state.downloader.get(id) else {
+                continue;
+            };
+            let entry = HistoryEntry::from(download);
+            let title = entry.title.clone().unwrap_or_else(|| entry.url.clone());
+            let files = download.files.len();
+            self.state.download_targets.remove(&id);
+
+            match &event {
+                DownloadEvent::Finished(_) if files > 1 => {
+                    self.notify(format!("Downloaded {} files of {}", files, title), None);
+                }
+                DownloadEvent::Finished(_) => {
+                    self.notify(format!("Downloaded {}", title), None);
+                }
+                DownloadEvent::Failed(_, error) => {
+                    self.notify(format!("{}\n{}", title, error), Some(entry.clone()));
+                }
+                _ => {}
+            }
+            self.options.record_download(entry);
+        }
+
+        // Keep the progress moving while nothing else repaints, and pick up the binary's check
+        let downloader = &self.state.downloader;
+        if downloader.is_active()
+            || downloader.is_checking()
+            || !self.state.notifications.is_empty()
+        {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+    }
+    fn notify(&mut self, text: String, retry: Option<HistoryEntry>) {
+        self.state.notifications.push(Notification {
+            text,
+            retry,
+            shown: Instant::now(),
+        });
+    }
+    fn notifications(&mut self, ctx: &egui::Context) {
+        /*
+            Notification area in the bottom right corner, failures stay until dismissed
+            and can be retried, everything else fades after a few seconds
+        */
+        self.state
+            .notifications
+            .retain(|n| n.retry.is_some() || n.shown.elapsed() < NOTIFICATION_TIME);
+        if self.state.notifications.is_empty() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("notifications"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .show(ctx, |ui| {
+                let mut dismissed = None;
+                for (i, notification) in self.state.notifications.clone().iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(260.0);
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").clicked() {
+                                dismissed = Some(i);
+                            }
+                            if let Some(entry) = &notification.retry {
+                                if ui.small_button("⟳").on_hover_text("Retry").clicked() {
+                                    self.retry_download(entry);
+                                    dismissed = Some(i);
+                                }
+                                ui.colored_label(Color32::RED, &notification.text);
+                            } else {
+                                ui.label(&notification.text);
+                            }
+                        });
+                    });
+                }
+                if let Some(i) = dismissed {
+                    self.state.notifications.remove(i);
+                }
+            });
+    }
+    fn format_bytes(bytes: f64) -> String {
+        /*
+            Human readable size in binary units, e.g. `1.5 MiB`
+        */
+        let units = ["B", "KiB", "MiB", "GiB"];
+        let mut size = bytes;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < units.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        format!("{:.1} {}", size, units[unit])
+    }
+    fn hotkeys_window(&mut self, ctx: &egui::Context) {
+        /*
+            Floating window listing the global hotkey bindings
+        */
+        let mut open = self.state.show_hotkeys;
+        egui::Window::new("Hotkeys")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if !self.hotkeys.available() {
+                    ui.colored_label(Color32::RED, "Global hotkeys are unavailable");
+                }
+
+                let mut changed = false;
+                let mut remove = None;
+
+                egui::Grid::new("hotkeys").striped(true).show(ui, |ui| {
+                    for (i, binding) in self.options.hotkeys.iter().enumerate() {
+                        ui.monospace(&binding.chord);
+                        ui.label(binding.target.label());
+
+                        if ui.small_button("Rebind").clicked() {
+                            self.state.rebinding = Some(Rebind::Existing(i));
+                            self.state.hotkey_conflict = None;
+                        }
+                        if ui
+                            .small_button(egui::RichText::new("Remove").color(Color32::RED))
+                            .clicked()
+                        {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                if let Some(i) = remove {
+                    self.options.hotkeys.remove(i);
+                    self.state.rebinding = None;
+                    changed = true;
+                }
+
+                ui.separator();
+
+                // Choose what a new binding should trigger
+                ui.horizontal(|ui| {
+                    let selected_text = match &self.state.hotkey_target {
+                        Some(target) => target.label(),
+                        None => "Select action".to_string(),
+                    };
+                    egui::ComboBox::from_id_source("hotkey_target")
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            let actions = [
+                                PlayerAction::Play,
+                                PlayerAction::Pause,
+                                PlayerAction::PlayPause,
+                                PlayerAction::Stop,
+                                PlayerAction::Next,
+                                PlayerAction::Previous,
+                            ];
+                            let pads = self
+                                .options
+                                .pads
+                                .iter()
+                                .map(|p| HotkeyTarget::Pad(Box::new(p.resource.clone())));
+                            let targets = actions.into_iter().map(HotkeyTarget::Action).chain(pads);
+
+                            for target in targets {
+                                let label = target.label();
+                                ui.selectable_value(
+                                    &mut self.state.hotkey_target,
+                                    Some(target),
+                                    label,
+                                );
+                            }
+                        });
+
+                    if ui
+                        .add_enabled(self.state.hotkey_target.is_some(), egui::Button::new("Add"))
+                        .clicked()
+                    {
+                        let target = self.state.hotkey_target.clone().unwrap();
+                        self.state.rebinding = Some(Rebind::New(target));
+                        self.state.hotkey_conflict = None;
+                    }
+                });
+
+                // Wait for the chord of the binding being (re)bound
+                if let Some(rebind) = self.state.rebinding.clone() {
+                    let except = match rebind {
+                        Rebind::Existing(i) => Some(i),
+                        Rebind::New(_) => None,
+                    };
+
+                    if let Some((chord, conflict)) = self.state.hotkey_conflict.clone() {
+                        ui.colored_label(
+                            Color32::RED,
+                            format!(
+                                "{} is already bound to {}",
+                                chord,
+                                self.options.hotkeys[conflict].target.label()
+                            ),
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("Replace").clicked() {
+                                self.options.hotkeys.remove(conflict);
+                                let rebind = match rebind {
+                                    Rebind::Existing(i) if i > conflict => Rebind::Existing(i - 1),
+                                    other => other,
+                                };
+                                self.apply_binding(rebind, chord);
+                                changed = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.state.rebinding = None;
+                                self.state.hotkey_conflict = None;
+                            }
+                        });
+                    } else {
+                        ui.label("Press a key combination, Escape to cancel");
+
+                        if let Some(chord) = ui.input(Self::pressed_chord) {
+                            if chord == "Escape" {
+                                self.state.rebinding = None;
+                            } else if let Some(conflict) =
+                                hotkeys::find_conflict(&self.options.hotkeys, &chord, except)
+                            {
+                                self.state.hotkey_conflict = Some((chord, conflict));
+                            } else {
+                                self.apply_binding(rebind, chord);
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+
+                if changed {
+                    self.state.hotkey_errors = self.hotkeys.register(&self.options.hotkeys);
+                }
+                for error in &self.state.hotkey_errors {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+
+        self.state.show_hotkeys = open;
+        if !open {
+            self.state.rebinding = None;
+            self.state.hotkey_conflict = None;
+        }
+    }
+    fn apply_binding(&mut self, rebind: Rebind, chord: String) {
+        match rebind {
+            Rebind::Existing(i) => self.options.hotkeys[i].chord = chord,
+            Rebind::New(target) => self.options.hotkeys.push(Binding { chord, target }),
+        }
+        self.state.rebinding = None;
+        self.state.hotkey_conflict = None;
+    }
+    fn pressed_chord(input: &egui::InputState) -> Option<String> {
+        /*
+            Convert the first key pressed this frame into a global-hotkey chord
+        */
+        input.events.iter().find_map(|event| {
+            let egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } = event
+            else {
+                return None;
+            };
+
+            // global-hotkey names a few keys differently than egui
+            let name = match key {
+                egui::Key::Equals => "Equal",
+                egui::Key::Backtick => "Backquote",
+                egui::Key::OpenBracket => "BracketLeft",
+                egui::Key::CloseBracket => "BracketRight",
+                _ => key.name(),
+            };
+
+            let mut chord = String::new();
+            if modifiers.shift {
+                chord.push_str("shift+");
+            }
+            if modifiers.ctrl {
+                chord.push_str("control+");
+            }
+            if modifiers.alt {
+                chord.push_str("alt+");
+            }
+            if modifiers.mac_cmd {
+                chord.push_str("super+");
+            }
+            chord.push_str(name);
+
+            // Store chords in global-hotkey's own spelling
+            Some(hotkeys::normalize_chord(&chord).unwrap_or(chord))
+        })
+    }
+    fn debug_window(&mut self, ctx: &egui::Context) {
+        /*
+            Floating window displaying information about the app's state
+        */
+        if self.options.show_debug {
+            egui::Window::new("Debug Menu").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Selected: ");
+                    let index = if let Some(selected) = self.options.playlist().selected.as_ref() {
+                        self.options
+                            .playlist()
+                            .resources
+                            .iter()
+                            .position(|r| r == selected)
+                            .unwrap()
+                    } else {
+                        0
+                    };
+
+                    ui.label(format!("{:?}", index));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Playing: ");
+                    let mut text = "None";
+                    if let Some(resource) = &self.player.current {
+                        text = resource.path.file_name().unwrap().to_str().unwrap();
+                    }
+                    ui.label(format!("{:?}", text));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Volume: ");
+                    ui.label(format!("{:?}", self.options.volume));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("UI Size: ");
+                    ui.label(format!("{:?}", self.options.ui_size));
+                });
+            });
+        }
+    }
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        /*
+            Media controls like Play/Pause/Stop
+        */
+        let frame = egui::Frame::default().fill(Color32::from_rgb(35, 35, 35));
+
+        // Controls are only active if a resource is selected to act upon
+        ui.add_enabled_ui(self.options.playlist().selected.is_some(), |ui| {
+            frame.show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    // Previous button
+                    if ui.button("⏮").clicked() {
+                        self.previous_track();
+                    }
+
+                    if self.player.is_playing() {
+                        // Pause button
+                        if ui.button("⏸").clicked() {
+                            self.player.pause();
                         }
                     } else {
                         // Play button
                         if ui.button("▶").clicked() {
-                            self.player.play(self.options.selected.clone().unwrap());
+                            self.play(self.options.playlist().selected.clone().unwrap());
                         }
                     }
 
@@ -515,6 +1763,11 @@ impl App {
                     if ui.button("⏹").clicked() {
                         self.player.stop();
                     }
+
+                    // Next button
+                    if ui.button("⏭").clicked() {
+                        self.next_track();
+                    }
                 });
             });
         });
@@ -555,22 +1808,52 @@ impl App {
         // otherwise the time-display would only update if the window changes
         ui.ctx().request_repaint_after(Duration::from_millis(250));
 
-        // get the currently playing resource's total duration
-        let time = match &self.player.current {
-            Some(resource) => resource.formatted_duration(),
-            None => "00:00".to_string(),
+        // get the elapsed/total time from the player
+        let Some(mut position) = self.player.position() else {
+            return;
         };
 
-        // get the elapsed time from the player
-        let elapsed = self.player.elapsed().unwrap();
-        // format the time to MM:SS format
-        let elapsed_string = format!(
-            "{:02}:{:02}",
-            elapsed.as_secs() / 60,
-            elapsed.as_secs() % 60
-        );
+        // While scrubbing show the position under the handle instead of the player's
+        if let Some(secs) = self.state.scrub {
+            position.elapsed = Duration::from_secs_f32(secs);
+        }
 
-        ui.label(format!("{}/{}", elapsed_string, time));
+        ui.label(position.formatted());
+    }
+    fn timeline(&mut self, ui: &mut egui::Ui) {
+        /*
+            Scrubbable progress bar of the playing resource
+        */
+        let Some(position) = self.player.position() else {
+            return;
+        };
+        if self.player.just_finished() {
+            return;
+        }
+
+        let total = position.total.as_secs_f32();
+        let mut secs = match self.state.scrub {
+            Some(secs) => secs,
+            None => position.elapsed.as_secs_f32(),
+        };
+
+        ui.spacing_mut().slider_width = ui.available_width();
+        let slider = egui::Slider::new(&mut secs, 0.0..=total)
+            .trailing_fill(true)
+            .show_value(false)
+            .handle_shape(egui::style::HandleShape::Rect {
+                aspect_ratio: (0.2),
+            });
+        let re = ui.add(slider);
+
+        // Only seek once the handle is let go, seeking every frame while dragging
+        // would restart the decoder constantly
+        if re.dragged() {
+            self.state.scrub = Some(secs);
+        } else if re.drag_stopped() || re.changed() {
+            self.state.scrub = None;
+            self.seek(Duration::from_secs_f32(secs));
+        }
     }
     fn handle_drop(&mut self, ctx: &egui::Context) {
         /*
@@ -589,12 +1872,28 @@ impl App {
         /*
             Handle key presses
         */
+
+        // While a hotkey is being bound the keys belong to the hotkey window,
+        // while a text field has focus they move its cursor
+        if self.state.rebinding.is_some() || ctx.wants_keyboard_input() {
+            return;
+        }
+
         ctx.input(|input| {
             // Press space to Plau / Pause
             if input.key_pressed(egui::Key::Space) {
                 self.play_pause();
             }
 
+            // `left/right` to skip 5 seconds, with `shift` to skip 30 seconds
+            let skip = Duration::from_secs(if input.modifiers.shift { 30 } else { 5 });
+            if input.key_pressed(egui::Key::ArrowLeft) {
+                self.skip(skip, true);
+            }
+            if input.key_pressed(egui::Key::ArrowRight) {
+                self.skip(skip, false);
+            }
+
             // `alt` + `-/+` to decrease / increase volume
             if input.modifiers.alt && input.key_pressed(egui::Key::Equals) {
                 self.options.volume = u8::clamp(self.options.volume + 1, 0, 100);
@@ -614,12 +1913,12 @@ impl App {
             if self.player.current.is_some() {
                 // If the player has a rsource, play / pause that
                 Some(self.player.current.as_ref().unwrap().clone())
-            } else if self.options.selected.is_some() {
+            } else if self.options.playlist().selected.is_some() {
                 // If a rource is selected, play / pause that
-                Some(self.options.selected.as_ref().unwrap().clone())
-            } else if !self.options.playlist.is_empty() {
+                Some(self.options.playlist().selected.as_ref().unwrap().clone())
+            } else if !self.options.playlist().resources.is_empty() {
                 // Else, play the first resource in the playlist
-                Some(self.options.playlist[0].clone())
+                Some(self.options.playlist().resources[0].clone())
             } else {
                 None
             }
@@ -632,8 +1931,338 @@ impl App {
         if self.player.is_playing() {
             self.player.pause();
         } else {
-            self.player.play(to_play.unwrap());
+            self.play(to_play.unwrap());
+        }
+    }
+    fn play(&mut self, resource: AudioResource) {
+        /*
+            Play `resource`, marking it as broken if it can't be decoded,
+            and remember the resource played before for `Previous`
+        */
+        let previous = self.player.current.clone();
+        match self.player.play(resource.clone()) {
+            Ok(()) if previous.as_ref() != Some(&resource) => {
+                let (playlist, queue) = self.options.playlist_and_queue();
+                queue.started(&resource, previous, playlist);
+            }
+            Ok(()) => {}
+            Err(e) => self.options.set_resource_error(&resource, e),
+        }
+    }
+    fn remove_resource(&mut self, resource: &AudioResource) {
+        // Neither keep playing a removed resource nor let autoplay continue with it
+        if let Err(e) = self.options.remove_resource(resource) {
+            let path = resource.path.display();
+            self.state.playlist_error = Some(format!("Could not delete {}: {}", path, e));
+        }
+        if self.player.preloaded() == Some(resource) {
+            self.player.cancel_preload();
+        }
+        if self.player.current.as_ref() == Some(resource) {
+            self.player.stop();
+        }
+    }
+    fn play_pad(&mut self, pad: &Pad) {
+        if let Err(e) = self.soundboard.play(pad) {
+            self.options.set_resource_error(&pad.resource, e);
+        }
+    }
+    fn seek(&mut self, position: Duration) {
+        let current = self.player.current.clone();
+        if let (Err(e), Some(current)) = (self.player.seek(position), current) {
+            self.options.set_resource_error(&current, e);
+        }
+    }
+    fn skip(&mut self, by: Duration, back: bool) {
+        let current = self.player.current.clone();
+        if let (Err(e), Some(current)) = (self.player.skip(by, back), current) {
+            self.options.set_resource_error(&current, e);
+        }
+    }
+    fn soundboard_outputs(&mut self, ui: &mut egui::Ui) {
+        /*
+            Extra outputs the soundboard plays to next to the monitor device,
+            each with its own gain
+        */
+        let mut changed = false;
+        let mut remove = None;
+
+        ui.label("Pads also play on:");
+        egui::Grid::new("soundboard_routes").show(ui, |ui| {
+            for (i, route) in self.options.soundboard_routes.iter_mut().enumerate() {
+                changed |= ui.checkbox(&mut route.enabled, &route.device).changed();
+
+                let slider = egui::Slider::new(&mut route.gain, 0..=100)
+                    .trailing_fill(true)
+                    .show_value(true);
+                if ui.add(slider).changed() {
+                    self.soundboard
+                        .set_route_gain_100(&route.device, route.gain);
+                }
+
+                if ui
+                    .small_button(egui::RichText::new("Remove").color(Color32::RED))
+                    .clicked()
+                {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(i) = remove {
+            self.options.soundboard_routes.remove(i);
+            changed = true;
+        }
+
+        // Add another device as a route
+        ui.menu_button("Add Output", |ui| {
+            for name in self.state.devices.names.clone() {
+                let used = self
+                    .options
+                    .soundboard_routes
+                    .iter()
+                    .any(|r| r.device == name);
+                if ui.add_enabled(!used, egui::Button::new(&name)).clicked() {
+                    self.options.soundboard_routes.push(OutputRoute::new(name));
+                    changed = true;
+                    ui.close_menu();
+                }
+            }
+        });
+
+        if changed {
+            self.state.route_errors = self.soundboard.set_routes(&self.options.soundboard_routes);
+        }
+        for error in &self.state.route_errors {
+            ui.colored_label(Color32::RED, error);
+        }
+    }
+    fn switch_output_device(&mut self) {
+        /*
+            Move the player and soundboard to the configured output device
+        */
+        let result = self.player.set_device(self.options.output_device.clone());
+        self.state.device_error = Self::device_error(&self.player, result);
+        self.soundboard
+            .set_stream_handle(self.player.stream_handle().cloned());
+    }
+    fn handle_output_device(&mut self) {
+        /*
+            Whenever the watcher saw the devices change,
+            check whether the output device disappeared or came back
+        */
+        let Some(devices) = self.device_watcher.poll() else {
+            return;
+        };
+        if self
+            .player
+            .check_device(self.options.output_device.as_deref(), &devices)
+        {
+            self.state.device_error = Self::device_error(&self.player, Ok(()));
+            self.soundboard
+                .set_stream_handle(self.player.stream_handle().cloned());
+        }
+        self.state.devices = devices;
+    }
+    fn device_error(
+        player: &AudioPlayer,
+        result: Result<(), rodio::StreamError>,
+    ) -> Option<String> {
+        if !player.has_output() {
+            return Some("No audio output device available".to_string());
         }
+        match result {
+            Ok(()) => None,
+            Err(e) => Some(format!(
+                "Output device unavailable, using the default ({})",
+                e
+            )),
+        }
+    }
+    fn handle_hotkeys(&mut self) {
+        /*
+            Handle global hotkeys pressed since the last frame
+        */
+        for target in self.hotkeys.poll() {
+            match target {
+                HotkeyTarget::Action(action) => self.apply_action(action),
+                HotkeyTarget::Pad(resource) => {
+                    // Use the pad's saved volume, if the pad still exists
+                    let pad = self
+                        .options
+                        .pads
+                        .iter()
+                        .find(|p| p.resource == *resource)
+                        .cloned()
+                        .unwrap_or_else(|| Pad::new(*resource));
+                    self.play_pad(&pad);
+                }
+            }
+        }
+    }
+    fn apply_action(&mut self, action: PlayerAction) {
+        match action {
+            PlayerAction::Play => {
+                let resource = self.player.current.clone().or(self
+                    .options
+                    .playlist()
+                    .selected
+                    .clone());
+                if let Some(resource) = resource {
+                    self.play(resource);
+                }
+            }
+            PlayerAction::Pause => self.player.pause(),
+            PlayerAction::PlayPause => self.play_pause(),
+            PlayerAction::Stop => self.player.stop(),
+            PlayerAction::Next => self.next_track(),
+            PlayerAction::Previous => self.previous_track(),
+            PlayerAction::Forward(by) => self.skip(by, false),
+            PlayerAction::Back(by) => self.skip(by, true),
+            PlayerAction::Seek(position) => self.seek(position),
+            PlayerAction::SetVolume(volume) => self.options.volume = volume.min(100),
+            PlayerAction::None => {}
+        }
+    }
+    fn handle_media_controls(&mut self) {
+        /*
+            Apply media keys and requests from the OS, then publish the player's state back
+        */
+        for action in self.media_controls.poll() {
+            self.apply_action(action);
+        }
+        self.media_controls
+            .update(&self.player, self.options.volume);
+    }
+    fn next_track(&mut self) {
+        /*
+            Play the first queued resource, or the one after the playing (or selected) one
+        */
+        let current = self
+            .player
+            .current
+            .as_ref()
+            .or(self.options.playlist().selected.as_ref());
+        if let Some(next) = self
+            .options
+            .queue
+            .skip_target(current, &self.options.playlist().resources)
+        {
+            self.play(next);
+        }
+    }
+    fn previous_track(&mut self) {
+        /*
+            Restart the playing resource, or go back to the one played before it
+            if it only just started
+        */
+        if self
+            .player
+            .position()
+            .is_some_and(|p| p.elapsed > Duration::from_secs(3))
+        {
+            self.seek(Duration::ZERO);
+            return;
+        }
+
+        // Going back doesn't count as playing something new, so it skips the history
+        let Some(previous) = self.options.queue.previous() else {
+            return;
+        };
+        if let Err(e) = self.player.play(previous.clone()) {
+            self.options.set_resource_error(&previous, e);
+        }
+    }
+    fn queue_panel(&mut self, ctx: &egui::Context) {
+        /*
+            Side panel listing the queued resources, which can be reordered and removed
+        */
+        egui::SidePanel::right("queue")
+            .resizable(true)
+            .default_width(160.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Up Next");
+                    if ui
+                        .add_enabled(!self.options.queue.is_empty(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        self.options.queue.clear();
+                    }
+                });
+                ui.separator();
+
+                if self.options.queue.is_empty() {
+                    ui.weak("Nothing queued, the playlist continues");
+                    return;
+                }
+
+                let queued: Vec<AudioResource> = self.options.queue.queued().cloned().collect();
+                let last = queued.len() - 1;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, resource) in queued.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(i > 0, egui::Button::new("⏶").small())
+                                .clicked()
+                            {
+                                self.options.queue.move_item(i, i - 1);
+                            }
+                            if ui
+                                .add_enabled(i < last, egui::Button::new("⏷").small())
+                                .clicked()
+                            {
+                                self.options.queue.move_item(i, i + 1);
+                            }
+                            if ui.small_button("✖").clicked() {
+                                self.options.queue.remove(i);
+                            }
+                            ui.label(resource.display_name());
+                        });
+                    }
+                });
+            });
+    }
+    fn now_playing(&mut self, ctx: &egui::Context) {
+        /*
+            Bottom panel with the cover and tags of the playing resource
+        */
+        let Some(current) = self.player.current.clone() else {
+            return;
+        };
+
+        // Only load the cover again once the track changed
+        if self.cover.as_ref().map(|(r, _)| r) != Some(&current) {
+            let texture = Self::load_cover(ctx, &current);
+            self.cover = Some((current.clone(), texture));
+        }
+        let texture = self.cover.as_ref().and_then(|(_, t)| t.as_ref());
+
+        egui::TopBottomPanel::bottom("now_playing").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(texture) = texture {
+                    ui.add(egui::Image::new(texture).max_size(egui::Vec2::splat(64.0)));
+                }
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new(current.display_name()).strong());
+                    let metadata = &current.metadata;
+                    if let Some(artist) = &metadata.artist {
+                        ui.label(artist);
+                    }
+                    if let Some(album) = &metadata.album {
+                        ui.label(egui::RichText::new(album).weak());
+                    }
+                });
+            });
+        });
+    }
+    fn load_cover(ctx: &egui::Context, resource: &AudioResource) -> Option<egui::TextureHandle> {
+        let path = resource.cover_art()?;
+        let image = image::open(path).ok()?.to_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        let image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+        Some(ctx.load_texture("cover", image, egui::TextureOptions::LINEAR))
     }
     fn central_panel(&mut self, ctx: &egui::Context) {
         /*
@@ -646,30 +2275,71 @@ impl App {
                     self.time(ui);
                     self.volume(ui);
                 });
+                self.timeline(ui);
                 ui.add_space(5.0);
-                self.playlist(ui);
+                match self.options.view {
+                    View::Playlist => self.playlist(ui),
+                    View::Soundboard => self.pads(ui),
+                }
             });
         });
     }
     fn handle_autoplay(&mut self) {
-        if self.options.autoplay && self.player.just_finished() {
-            // get the index of the selected resource
-            let index = self
-                .options
-                .playlist
-                .iter()
-                .position(|r| r == self.options.selected.as_ref().unwrap())
-                .unwrap();
+        self.player.update();
 
-            // get the next resource in the playlist
-            let next = self
+        // Follow the player onto the preloaded resource once it started playing
+        if let Some(previous) = self.player.advance() {
+            if let Some(current) = &self.player.current {
+                let (playlist, queue) = self.options.playlist_and_queue();
+                queue.started(current, previous, playlist);
+            }
+        }
+
+        if !self.options.autoplay {
+            self.player.cancel_preload();
+            return;
+        }
+
+        // get the resource to play after the playing one
+        let next = match &self.player.current {
+            Some(current) => self
                 .options
-                .playlist
-                .get((index + 1) % self.options.playlist.len());
+                .queue
+                .upcoming(Some(current), &self.options.playlist().resources),
+            None => None,
+        };
 
-            // Select and play the next resource
-            self.options.selected = next.cloned();
-            self.player.play(next.unwrap().clone());
+        if self.player.crossfade().is_zero() {
+            // Keep it queued in the player, so it follows without a gap
+            match &next {
+                Some(next) if next.playable() => {
+                    if let Err(e) = self.player.preload(next.clone()) {
+                        self.options.set_resource_error(next, e);
+                    }
+                }
+                _ => self.player.cancel_preload(),
+            }
+        } else {
+            // Start it once the playing one reaches its crossfade,
+            // tracks shorter than two crossfades get half each.
+            // Repeating one resource can't fade into itself, it restarts once it ended
+            let crossfade = self.player.crossfade();
+            let repeating = next.is_some() && next == self.player.current;
+            let fading = self.player.is_playing()
+                && !repeating
+                && self.player.position().is_some_and(|p| {
+                    p.remaining() <= crossfade && p.elapsed >= crossfade.min(p.total / 2)
+                });
+            if let Some(next) = next.clone().filter(|n| fading && n.playable()) {
+                self.play(next);
+            }
+        }
+
+        // Preloading failed or the playing resource ended without successor
+        if self.player.just_finished() {
+            if let Some(next) = next {
+                self.play(next);
+            }
         }
     }
 }
@@ -679,31 +2349,58 @@ impl eframe::App for App {
         // Handle events
         self.handle_drop(ctx);
         self.handle_keys(ctx);
+        self.handle_hotkeys();
+        self.handle_media_controls();
+        self.handle_output_device();
         self.handle_autoplay();
 
         // Render ui elements
         self.menu(ctx);
+        if self.options.show_now_playing {
+            self.now_playing(ctx);
+        }
+        if self.options.show_queue {
+            self.queue_panel(ctx);
+        }
         self.central_panel(ctx);
 
         if self.options.show_debug {
             self.debug_window(ctx);
         }
-
-        // if the downloader is finished downloading add the files to the playlist
-        // todo: improve keeping track of downloaded resources
-        if self.state.downloader.is_finished() {
-            self.options.add_downloads();
+        if self.state.show_hotkeys {
+            self.hotkeys_window(ctx);
+        }
+        if self.state.show_downloads {
+            self.downloads_window(ctx);
         }
+        if self.state.show_yt_dlp_settings {
+            self.yt_dlp_window(ctx);
+        }
+
+        self.notifications(ctx);
+        self.handle_downloads(ctx);
 
         //  Change volume if changed in ui
         if self.options.volume != self.player.volume() as u8 * 100 {
             self.player.set_volume_100(self.options.volume);
+            self.soundboard.set_master_volume_100(self.options.volume);
         }
 
         // save the windows size to the options
         self.options.ui_size = ctx.screen_rect().max.into();
     }
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        self.options.save_to_json("sours.json");
+        /*
+            The window is gone by now, so failures are shown in a dialog like load failures
+        */
+        let config = &paths::get().config;
+        if let Err(e) = self.options.save_to_json(config) {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("sours")
+                .set_description(format!("Could not save {}: {}", config.display(), e))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+        }
     }
 }
@@ -0,0 +1,119 @@
+use lofty::{Accessor, AudioFile, ItemKey, TaggedFile, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+
+/// Tags and audio properties of a file, as read by lofty
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct AudioMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    /// In kbps
+    pub bitrate: Option<u32>,
+    /// In Hz
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+}
+
+impl AudioMetadata {
+    pub fn from_tagged_file(tagged: &TaggedFile) -> Self {
+        let properties = tagged.properties();
+        let mut metadata = Self {
+            bitrate: properties
+                .audio_bitrate()
+                .or_else(|| properties.overall_bitrate()),
+            sample_rate: properties.sample_rate(),
+            channels: properties.channels(),
+            ..Default::default()
+        };
+
+        // Files may carry several tags, prefer the one native to the format
+        let Some(tag) = tagged.primary_tag().or_else(|| tagged.first_tag()) else {
+            return metadata;
+        };
+        metadata.title = tag.title().map(|s| s.to_string());
+        metadata.artist = tag.artist().map(|s| s.to_string());
+        metadata.album = tag.album().map(|s| s.to_string());
+        metadata.album_artist = tag.get_string(&ItemKey::AlbumArtist).map(String::from);
+        metadata.track = tag.track();
+        metadata.disc = tag.disk();
+        metadata.year = tag.year();
+        metadata.genre = tag.genre().map(|s| s.to_string());
+        metadata
+    }
+}
+
+/// A column of the playlist view
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    FileName,
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Track,
+    Disc,
+    Year,
+    Genre,
+    Duration,
+    Bitrate,
+    SampleRate,
+    Channels,
+}
+
+impl Column {
+    pub const ALL: [Column; 13] = [
+        Column::FileName,
+        Column::Title,
+        Column::Artist,
+        Column::Album,
+        Column::AlbumArtist,
+        Column::Track,
+        Column::Disc,
+        Column::Year,
+        Column::Genre,
+        Column::Duration,
+        Column::Bitrate,
+        Column::SampleRate,
+        Column::Channels,
+    ];
+
+    /// The columns shown until the user picks their own
+    pub fn defaults() -> Vec<Column> {
+        vec![Column::Title, Column::Artist, Column::Duration]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FileName => "File",
+            Self::Title => "Title",
+            Self::Artist => "Artist",
+            Self::Album => "Album",
+            Self::AlbumArtist => "Album Artist",
+            Self::Track => "#",
+            Self::Disc => "Disc",
+            Self::Year => "Year",
+            Self::Genre => "Genre",
+            Self::Duration => "Length",
+            Self::Bitrate => "Bitrate",
+            Self::SampleRate => "Sample Rate",
+            Self::Channels => "Ch",
+        }
+    }
+
+    pub fn width(self) -> f32 {
+        match self {
+            Self::FileName | Self::Title => 180.0,
+            Self::Artist | Self::Album | Self::AlbumArtist => 120.0,
+            Self::Genre => 80.0,
+            Self::Bitrate | Self::SampleRate => 70.0,
+            Self::Duration | Self::Year => 45.0,
+            Self::Track | Self::Disc | Self::Channels => 30.0,
+        }
+    }
+}
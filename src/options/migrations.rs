@@ -0,0 +1,41 @@
+use serde_json::{json, Map, Value};
+
+use crate::playlist;
+
+/// Version of the options this build writes
+pub const CURRENT_VERSION: u32 = 1;
+
+/// One step of the chain, `MIGRATIONS[n]` turns version `n` into version `n + 1`
+type Migration = fn(&mut Map<String, Value>);
+
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [named_playlists];
+
+/// Bring options written by any older version up to `CURRENT_VERSION`,
+/// files without a `version` are version 0. Newer files are left as they are
+pub fn migrate(json: &mut Value) {
+    let Some(options) = json.as_object_mut() else {
+        return;
+    };
+    let version = options.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    for (from, migration) in MIGRATIONS.iter().enumerate() {
+        if from as u64 >= version {
+            migration(options);
+            options.insert("version".to_string(), json!(from + 1));
+        }
+    }
+}
+
+/// 0 → 1: the single `playlist` and its `selected` resource move into a "Default" playlist
+fn named_playlists(options: &mut Map<String, Value>) {
+    let resources = options.remove("playlist").unwrap_or_else(|| json!([]));
+    let selected = options.remove("selected").unwrap_or(Value::Null);
+    if !options.contains_key("playlists") {
+        let default = json!({
+            "name": playlist::DEFAULT_NAME,
+            "resources": resources,
+            "selected": selected,
+        });
+        options.insert("playlists".to_string(), json!([default]));
+    }
+}
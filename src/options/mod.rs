@@ -0,0 +1,494 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::audio_resource::AudioResource;
+use crate::error::SoursError;
+use crate::formats;
+use crate::hotkeys::Binding;
+use crate::metadata::Column;
+use crate::paths;
+use crate::play_queue::PlayQueue;
+use crate::playlist::{self, Playlist, PlaylistId};
+use crate::soundboard::{OutputRoute, Pad};
+use crate::ytdlp::{DownloadedFile, HistoryEntry, YtDlpSettings};
+use serde::{Deserialize, Serialize};
+
+mod migrations;
+mod playlist_file;
+
+pub use migrations::CURRENT_VERSION;
+pub use playlist_file::{export_playlist, import_playlist, PlaylistFormat};
+
+/// How many ended downloads are kept in the history
+const MAX_DOWNLOAD_HISTORY: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum View {
+    #[default]
+    Playlist,
+    Soundboard,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Options {
+    /// Version of the file these were read from, see `migrations`
+    pub version: u32,
+    pub playlists: Vec<Playlist>,
+    /// Index into `playlists` of the one shown and played from
+    pub active_playlist: usize,
+    // pub downloads: Vec<AudioResource>,
+    pub autoplay: bool,
+    pub volume: u8,
+    pub ui_size: [f32; 2],
+    pub show_debug: bool,
+    pub always_on_top: bool,
+    pub pads: Vec<Pad>,
+    pub view: View,
+    pub hotkeys: Vec<Binding>,
+    pub output_device: Option<String>,
+    pub soundboard_routes: Vec<OutputRoute>,
+    pub playlist_columns: Vec<Column>,
+    pub show_now_playing: bool,
+    /// Seconds the end of a track overlaps the start of the next, 0 to 12
+    pub crossfade_secs: u8,
+    pub fade_pause_stop: bool,
+    pub queue: PlayQueue,
+    pub show_queue: bool,
+    /// Ended downloads, most recent last
+    pub download_history: Vec<HistoryEntry>,
+    pub yt_dlp: YtDlpSettings,
+    // logs: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            version: migrations::CURRENT_VERSION,
+            playlists: vec![Playlist::new(playlist::DEFAULT_NAME)],
+            active_playlist: 0,
+            autoplay: false,
+            volume: 50,
+            ui_size: [360.0, 300.0],
+            show_debug: false,
+            always_on_top: false,
+            pads: Vec::new(),
+            view: View::Playlist,
+            hotkeys: Vec::new(),
+            output_device: None,
+            soundboard_routes: Vec::new(),
+            playlist_columns: Column::defaults(),
+            show_now_playing: true,
+            crossfade_secs: 0,
+            fade_pause_stop: false,
+            queue: PlayQueue::default(),
+            show_queue: false,
+            download_history: Vec::new(),
+            yt_dlp: YtDlpSettings::default(),
+            // downloads: Vec::new(),
+            // logs: Vec::new()
+        }
+    }
+}
+
+/// Why the options couldn't be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read the file: {}", e),
+            Self::Parse(e) => write!(f, "The file is not valid: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl Options {
+    pub fn save_to_json(&self, path: &Path) -> io::Result<()> {
+        /*
+            Write to a temporary file and move that over the old one,
+            so a crash while saving can't leave a half-written file behind.
+            Options read from a newer version are never written back,
+            that would drop everything this version doesn't know about
+        */
+        if self.version > migrations::CURRENT_VERSION {
+            return Err(io::Error::other(format!(
+                "the file is from a newer version of sours (version {}, this one writes {})",
+                self.version,
+                migrations::CURRENT_VERSION
+            )));
+        }
+        let json = serde_json::to_string_pretty(&self)?;
+        let temporary = with_suffix(path, ".tmp");
+
+        let mut file = File::create(&temporary)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, path)
+    }
+
+    pub fn load_from_json(path: &Path) -> Result<Self, LoadError> {
+        if !path.exists() {
+            Options::default().save_to_json(path)?;
+        }
+
+        let json = std::fs::read_to_string(path)?;
+        let mut json: serde_json::Value = serde_json::from_str(&json)?;
+        migrations::migrate(&mut json);
+        let mut options: Options = serde_json::from_value(json)?;
+
+        // There always is a playlist to show
+        if options.playlists.is_empty() {
+            options
+                .playlists
+                .push(Playlist::new(playlist::DEFAULT_NAME));
+        }
+        if options.active_playlist >= options.playlists.len() {
+            options.active_playlist = 0;
+        }
+
+        // Keep the file as the last one known to load, a failed backup doesn't stop sours
+        let _ = std::fs::copy(path, Self::backup_path(path));
+        Ok(options)
+    }
+
+    /// Where the last options that loaded are kept
+    pub fn backup_path(path: &Path) -> PathBuf {
+        with_suffix(path, ".bak")
+    }
+
+    /// Where options that couldn't be loaded are moved by `restore_backup` and `reset`,
+    /// numbered so earlier broken copies are kept
+    pub fn broken_path(path: &Path) -> PathBuf {
+        let broken = with_suffix(path, ".broken");
+        (1..)
+            .map(|n| match n {
+                1 => broken.clone(),
+                n => with_suffix(&broken, &format!(".{}", n)),
+            })
+            .find(|broken| !broken.exists())
+            .unwrap()
+    }
+
+    /// Move the options at `path` aside and load the last ones that loaded instead
+    pub fn restore_backup(path: &Path) -> Result<Self, LoadError> {
+        std::fs::rename(path, Self::broken_path(path))?;
+        std::fs::copy(Self::backup_path(path), path)?;
+        Self::load_from_json(path)
+    }
+
+    /// Move the options at `path` aside and start over with the defaults
+    pub fn reset(path: &Path) -> Result<Self, LoadError> {
+        std::fs::rename(path, Self::broken_path(path))?;
+        Self::load_from_json(path)
+    }
+
+    /// The playlist that is shown and played from
+    pub fn playlist(&self) -> &Playlist {
+        &self.playlists[self.active_playlist]
+    }
+
+    pub fn playlist_mut(&mut self) -> &mut Playlist {
+        &mut self.playlists[self.active_playlist]
+    }
+
+    /// The active playlist's resources together with the queue playing through them
+    pub fn playlist_and_queue(&mut self) -> (&[AudioResource], &mut PlayQueue) {
+        let playlist = &self.playlists[self.active_playlist].resources;
+        (playlist, &mut self.queue)
+    }
+
+    /// Show and play from the playlist at `index`, its selection is restored as it was left
+    pub fn switch_playlist(&mut self, index: usize) {
+        if index < self.playlists.len() {
+            self.active_playlist = index;
+        }
+    }
+
+    /// Add an empty playlist called `name` (made unique) and switch to it
+    pub fn create_playlist(&mut self, name: &str) {
+        let name = playlist::unique_name(name, &self.playlists);
+        self.playlists.push(Playlist::new(name));
+        self.active_playlist = self.playlists.len() - 1;
+    }
+
+    pub fn rename_playlist(&mut self, index: usize, name: &str) {
+        if self.playlists.get(index).is_some_and(|p| p.name == name) {
+            return;
+        }
+        let name = playlist::unique_name(name, &self.playlists);
+        if let Some(playlist) = self.playlists.get_mut(index) {
+            playlist.name = name;
+        }
+    }
+
+    /// Add a copy of the playlist at `index` right after it and switch to the copy
+    pub fn duplicate_playlist(&mut self, index: usize) {
+        let Some(original) = self.playlists.get(index) else {
+            return;
+        };
+        let mut copy = original.clone();
+        copy.id = playlist::next_id();
+        copy.name = playlist::unique_name(&format!("{} (copy)", original.name), &self.playlists);
+        self.playlists.insert(index + 1, copy);
+        self.active_playlist = index + 1;
+    }
+
+    /// Delete the playlist at `index`, its resources' files are kept.
+    /// The last playlist can't be deleted
+    pub fn delete_playlist(&mut self, index: usize) {
+        if self.playlists.len() <= 1 || index >= self.playlists.len() {
+            return;
+        }
+        self.playlists.remove(index);
+        if self.active_playlist > index || self.active_playlist >= self.playlists.len() {
+            self.active_playlist = self.active_playlist.saturating_sub(1);
+        }
+    }
+
+    /// Where the playlist `id` is, `None` if it was deleted
+    pub fn playlist_index(&self, id: PlaylistId) -> Option<usize> {
+        self.playlists.iter().position(|p| p.id == id)
+    }
+
+    /// Add the playlist file at `path` as a new playlist and switch to it
+    pub fn import_playlist(&mut self, path: &Path) -> Result<(), SoursError> {
+        let mut imported = playlist_file::import_playlist(path)?;
+        imported.name = playlist::unique_name(&imported.name, &self.playlists);
+        self.playlists.push(imported);
+        self.active_playlist = self.playlists.len() - 1;
+        Ok(())
+    }
+
+    /// Write the active playlist to `path`, in the format its extension names
+    pub fn export_playlist(&self, path: &Path) -> Result<(), SoursError> {
+        playlist_file::export_playlist(self.playlist(), path)
+    }
+
+    fn in_any_playlist(&self, resource: &AudioResource) -> bool {
+        self.playlists.iter().any(|p| p.contains(resource))
+    }
+
+    pub fn add_resource(&mut self, path: PathBuf) {
+        if formats::is_supported(&path) {
+            let resource = AudioResource::from_path(path.to_string_lossy().to_string());
+            if self.playlist().contains(&resource) {
+                return;
+            }
+            self.playlist_mut().resources.push(resource);
+        }
+    }
+
+    pub fn add_pad(&mut self, path: PathBuf) {
+        if formats::is_supported(&path) {
+            let resource = AudioResource::from_path(path.to_string_lossy().to_string());
+            if self.pads.iter().any(|p| p.resource == resource) {
+                return;
+            }
+            self.pads.push(Pad::new(resource));
+        }
+    }
+
+    pub fn set_resource_error(&mut self, resource: &AudioResource, error: SoursError) {
+        /*
+            Mark every entry of `resource` in the playlists and soundboard as broken
+        */
+        for playlist in &mut self.playlists {
+            if playlist.selected.as_ref() == Some(resource) {
+                playlist.selected = None;
+            }
+        }
+        let pads = self.pads.iter_mut().map(|p| &mut p.resource);
+        let entries = self
+            .playlists
+            .iter_mut()
+            .flat_map(|p| p.resources.iter_mut());
+        for entry in entries.chain(pads) {
+            if entry == resource {
+                entry.error = Some(error.clone());
+            }
+        }
+    }
+
+    pub fn remove_pad(&mut self, resource: &AudioResource) {
+        self.pads.retain(|p| &p.resource != resource);
+    }
+
+    /// Remove `resource` from the active playlist. Once no playlist has it anymore
+    /// it's also taken off the queue, and deleted if it was downloaded
+    pub fn remove_resource(&mut self, resource: &AudioResource) -> std::io::Result<()> {
+        self.playlist_mut().remove(resource);
+        if self.in_any_playlist(resource) {
+            return Ok(());
+        }
+        self.queue.remove_resource(resource);
+
+        delete_download(&resource.path, &paths::get().downloads)
+    }
+
+    /// Remember an ended download, forgetting the oldest beyond `MAX_DOWNLOAD_HISTORY`
+    pub fn record_download(&mut self, entry: HistoryEntry) {
+        self.download_history.push(entry);
+        let excess = self
+            .download_history
+            .len()
+            .saturating_sub(MAX_DOWNLOAD_HISTORY);
+        self.download_history.drain(..excess);
+    }
+
+    /// Add a file a download created to the playlist at `index`,
+    /// tags the file doesn't have are filled in from its video
+    pub fn add_downloaded(&mut self, file: &DownloadedFile, index: usize) {
+        if !formats::is_supported(&file.path) {
+            return;
+        }
+        let Some(playlist) = self.playlists.get_mut(index) else {
+            return;
+        };
+
+        let mut resource = AudioResource::from_path(file.path.to_string_lossy().to_string());
+        let metadata = &mut resource.metadata;
+        metadata.title = metadata.title.take().or_else(|| file.title.clone());
+        metadata.artist = metadata.artist.take().or_else(|| file.uploader.clone());
+        if !playlist.contains(&resource) {
+            playlist.resources.push(resource);
+        }
+    }
+
+    /// Remove every resource of the active playlist, the first error
+    /// deleting a download doesn't stop the others from being removed
+    pub fn playlist_clear(&mut self) -> std::io::Result<()> {
+        let pl_clone = self.playlist().resources.clone();
+        let mut result = Ok(());
+        for res in &pl_clone {
+            let removed = self.remove_resource(res);
+            result = result.and(removed);
+        }
+        result
+    }
+}
+
+/// Delete `path` if it's somewhere in `downloads`, subfolders included.
+/// A download that's already gone is as good as deleted
+fn delete_download(path: &Path, downloads: &Path) -> io::Result<()> {
+    let (Ok(path), Ok(downloads)) = (path.canonicalize(), downloads.canonicalize()) else {
+        return Ok(());
+    };
+    if !path.starts_with(&downloads) {
+        return Ok(());
+    }
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `sours.json.bak`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, json: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sours-options-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sours.json");
+        std::fs::write(&path, json).unwrap();
+        path
+    }
+
+    #[test]
+    fn newer_options_are_not_overwritten() {
+        let json = format!(
+            r#"{{"version": {}, "volume": 20, "from_the_future": true}}"#,
+            CURRENT_VERSION + 1
+        );
+        let path = config("newer", &json);
+
+        let options = Options::load_from_json(&path).unwrap();
+        assert_eq!(options.volume, 20);
+        assert!(options.save_to_json(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), json);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn broken_options_are_all_kept() {
+        let path = config("broken", "{");
+        assert!(Options::load_from_json(&path).is_err());
+        Options::reset(&path).unwrap();
+        std::fs::write(&path, "[").unwrap();
+        Options::reset(&path).unwrap();
+
+        let first = std::fs::read_to_string(with_suffix(&path, ".broken")).unwrap();
+        let second = std::fs::read_to_string(with_suffix(&path, ".broken.2")).unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("{", "["));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn older_options_are_saved_as_the_current_version() {
+        let path = config("older", r#"{"volume": 20, "playlist": []}"#);
+
+        let options = Options::load_from_json(&path).unwrap();
+        assert_eq!(options.version, CURRENT_VERSION);
+        options.save_to_json(&path).unwrap();
+        let saved = Options::load_from_json(&path).unwrap();
+        assert_eq!(saved.volume, 20);
+        assert_eq!(saved.playlists.len(), 1);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn downloads_in_subfolders_are_deleted_too() {
+        let dir = config("delete", "{}").parent().unwrap().to_path_buf();
+        let downloads = dir.join("downloads");
+        std::fs::create_dir_all(downloads.join("Channel")).unwrap();
+        let files = [
+            downloads.join("a.mp3"),
+            downloads.join("Channel/b.mp3"),
+            dir.join("c.mp3"),
+        ];
+        for file in &files {
+            std::fs::write(file, b"").unwrap();
+        }
+
+        delete_download(&files[0], &downloads).unwrap();
+        // Relative parts don't hide where a file really is
+        delete_download(&downloads.join("Channel/../Channel/b.mp3"), &downloads).unwrap();
+        delete_download(&downloads.join("../c.mp3"), &downloads).unwrap();
+        delete_download(&downloads.join("gone.mp3"), &downloads).unwrap();
+        assert!(!files[0].exists());
+        assert!(!files[1].exists());
+        assert!(files[2].exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
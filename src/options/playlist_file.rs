@@ -0,0 +1,614 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use quick_xml::{escape::escape, events::Event, Reader};
+use url::Url;
+
+use crate::audio_resource::AudioResource;
+use crate::error::SoursError;
+use crate::playlist::Playlist;
+
+/// Playlist files sours can import and export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub const ALL: [PlaylistFormat; 3] = [Self::M3u, Self::Pls, Self::Xspf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::M3u => "M3U",
+            Self::Pls => "PLS",
+            Self::Xspf => "XSPF",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::M3u => &["m3u", "m3u8"],
+            Self::Pls => &["pls"],
+            Self::Xspf => &["xspf"],
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+}
+
+/// What a playlist file says about one of its entries
+#[derive(Debug, Default)]
+struct Entry {
+    location: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    duration: Option<Duration>,
+}
+
+/// Read the playlist file at `path`. Relative entries are resolved against the file's
+/// directory, entries that aren't local files (e.g. streams) are left out
+pub fn import_playlist(path: &Path) -> Result<Playlist, SoursError> {
+    let format = PlaylistFormat::from_path(path).ok_or_else(|| unknown_format(path))?;
+    if !path.exists() {
+        return Err(SoursError::NotFound(path.to_path_buf()));
+    }
+
+    // M3U files that aren't M3U8 are often Latin-1, keep what can be read of them
+    let bytes = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let text = text.trim_start_matches('\u{feff}');
+
+    let base = base_dir(path)?;
+    let (name, entries, locate): (_, _, fn(&str, &Path) -> Option<PathBuf>) = match format {
+        PlaylistFormat::M3u => {
+            let (name, entries) = parse_m3u(text);
+            (name, entries, resolve_path)
+        }
+        PlaylistFormat::Pls => (None, parse_pls(text), resolve_path),
+        PlaylistFormat::Xspf => {
+            let (name, entries) = parse_xspf(text)?;
+            (name, entries, resolve_uri)
+        }
+    };
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut playlist = Playlist::new(name.unwrap_or_else(|| stem.to_string()));
+    for entry in entries {
+        let Some(file) = locate(&entry.location, &base) else {
+            continue;
+        };
+        let resource = resource(file, entry);
+        if !playlist.contains(&resource) {
+            playlist.resources.push(resource);
+        }
+    }
+    Ok(playlist)
+}
+
+/// Write `playlist` to `path`, in the format its extension names. Resources inside
+/// the file's directory are written relative to it, so both can be moved together
+pub fn export_playlist(playlist: &Playlist, path: &Path) -> Result<(), SoursError> {
+    let format = PlaylistFormat::from_path(path).ok_or_else(|| unknown_format(path))?;
+    let base = base_dir(path)?;
+
+    let text = match format {
+        PlaylistFormat::M3u => write_m3u(playlist, &base)?,
+        PlaylistFormat::Pls => write_pls(playlist, &base)?,
+        PlaylistFormat::Xspf => write_xspf(playlist, &base)?,
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+fn unknown_format(path: &Path) -> SoursError {
+    SoursError::InvalidPlaylist(format!("unknown playlist format: {}", path.display()))
+}
+
+fn base_dir(path: &Path) -> Result<PathBuf, SoursError> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    absolute(dir)
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, SoursError> {
+    match path.is_absolute() {
+        true => Ok(path.to_path_buf()),
+        false => Ok(std::env::current_dir()?.join(path)),
+    }
+}
+
+fn resource(file: PathBuf, entry: Entry) -> AudioResource {
+    /*
+        Files that can't be read (yet) still show what the playlist knows about them
+    */
+    let mut resource = AudioResource::from_path(file.to_string_lossy().to_string());
+    let metadata = &mut resource.metadata;
+    metadata.title = metadata.title.take().or(entry.title);
+    metadata.artist = metadata.artist.take().or(entry.artist);
+    metadata.album = metadata.album.take().or(entry.album);
+    if resource.duration.is_zero() {
+        resource.duration = entry.duration.unwrap_or_default();
+    }
+    resource
+}
+
+fn resolve_path(location: &str, base: &Path) -> Option<PathBuf> {
+    /*
+        M3U and PLS entries are paths, or sometimes `file:` URIs
+    */
+    if location.contains("://") || location.starts_with("file:") {
+        return Url::parse(location).ok()?.to_file_path().ok();
+    }
+    Some(base.join(location))
+}
+
+fn resolve_uri(location: &str, base: &Path) -> Option<PathBuf> {
+    /*
+        XSPF locations are URIs, relative ones are relative to the playlist file
+    */
+    let base = Url::from_directory_path(base).ok()?;
+    base.join(location).ok()?.to_file_path().ok()
+}
+
+/// The path to write for `resource`, relative to `base` if it's inside it
+fn relative_path(resource: &AudioResource, base: &Path) -> Result<PathBuf, SoursError> {
+    let path = absolute(&resource.path)?;
+    match path.strip_prefix(base) {
+        Ok(relative) => Ok(relative.to_path_buf()),
+        Err(_) => Ok(path),
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Split the "Artist - Title" names players write into artist and title
+fn split_name(name: &str) -> (Option<String>, Option<String>) {
+    match name.split_once(" - ") {
+        Some((artist, title)) => (non_empty(artist), non_empty(title)),
+        None => (None, non_empty(name)),
+    }
+}
+
+/// The "Artist - Title" name to write for `resource`
+fn entry_name(resource: &AudioResource) -> String {
+    match (&resource.metadata.artist, &resource.metadata.title) {
+        (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+        _ => resource.display_name(),
+    }
+}
+
+/// Seconds, negative (or missing) for an unknown length
+fn parse_seconds(text: &str) -> Option<Duration> {
+    // Infinite and huge lengths can't be a `Duration`, they are unknown too
+    let seconds = text.trim().parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+}
+
+/// Seconds to write for `resource`, -1 if its length is unknown
+fn seconds(resource: &AudioResource) -> i64 {
+    match resource.duration.is_zero() {
+        true => -1,
+        false => resource.duration.as_secs() as i64,
+    }
+}
+
+fn parse_m3u(text: &str) -> (Option<String>, Vec<Entry>) {
+    /*
+        Every line not starting with `#` is an entry, an `#EXTINF:<seconds>,<name>`
+        line before it describes it
+    */
+    let mut name = None;
+    let mut entries = Vec::new();
+    let mut info: Option<Entry> = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // Attributes like `tvg-id="…"` may follow the seconds, their values can hold commas
+            let mut quoted = false;
+            let comma = extinf.find(|c| {
+                quoted ^= c == '"';
+                c == ',' && !quoted
+            });
+            let (head, entry_name) = match comma {
+                Some(comma) => (&extinf[..comma], &extinf[comma + 1..]),
+                None => (extinf, ""),
+            };
+            let (artist, title) = split_name(entry_name);
+            info = Some(Entry {
+                title,
+                artist,
+                duration: head.split_whitespace().next().and_then(parse_seconds),
+                ..Default::default()
+            });
+        } else if let Some(playlist) = line.strip_prefix("#PLAYLIST:") {
+            name = non_empty(playlist);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let mut entry = info.take().unwrap_or_default();
+            entry.location = line.to_string();
+            entries.push(entry);
+        }
+    }
+    (name, entries)
+}
+
+fn write_m3u(playlist: &Playlist, base: &Path) -> Result<String, SoursError> {
+    let mut text = String::from("#EXTM3U\n");
+    text.push_str(&format!("#PLAYLIST:{}\n", playlist.name));
+    for resource in &playlist.resources {
+        let path = relative_path(resource, base)?;
+        text.push_str(&format!(
+            "#EXTINF:{},{}\n",
+            seconds(resource),
+            entry_name(resource)
+        ));
+        text.push_str(&format!("{}\n", path.display()));
+    }
+    Ok(text)
+}
+
+fn parse_pls(text: &str) -> Vec<Entry> {
+    /*
+        Every entry is spread over numbered keys: `File1=`, `Title1=`, `Length1=`
+    */
+    let mut entries: BTreeMap<u32, Entry> = BTreeMap::new();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let numbered = ["file", "title", "length"].into_iter().find_map(|field| {
+            let number = key.strip_prefix(field)?.parse::<u32>().ok()?;
+            Some((field, number))
+        });
+        let Some((field, number)) = numbered else {
+            continue;
+        };
+
+        let entry = entries.entry(number).or_default();
+        match field {
+            "file" => entry.location = value.trim().to_string(),
+            "title" => (entry.artist, entry.title) = split_name(value),
+            _ => entry.duration = parse_seconds(value),
+        }
+    }
+
+    entries
+        .into_values()
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+fn write_pls(playlist: &Playlist, base: &Path) -> Result<String, SoursError> {
+    let mut text = String::from("[playlist]\n");
+    for (i, resource) in playlist.resources.iter().enumerate() {
+        let number = i + 1;
+        let path = relative_path(resource, base)?;
+        text.push_str(&format!("File{}={}\n", number, path.display()));
+        text.push_str(&format!("Title{}={}\n", number, entry_name(resource)));
+        text.push_str(&format!("Length{}={}\n", number, seconds(resource)));
+    }
+    text.push_str(&format!(
+        "NumberOfEntries={}\nVersion=2\n",
+        playlist.resources.len()
+    ));
+    Ok(text)
+}
+
+fn parse_xspf(text: &str) -> Result<(Option<String>, Vec<Entry>), SoursError> {
+    /*
+        Only the playlist's title and each track's location, title, creator,
+        album and duration (in milliseconds) are read, everything else is skipped
+    */
+    let invalid = |e: quick_xml::Error| SoursError::InvalidPlaylist(e.to_string());
+
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut name = None;
+    let mut entries = Vec::new();
+    // Names of the elements the reader is in, outermost first
+    let mut elements: Vec<Vec<u8>> = Vec::new();
+    let mut track: Option<Entry> = None;
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(start) => {
+                let element = start.local_name().as_ref().to_vec();
+                if element == b"track" {
+                    track = Some(Entry::default());
+                }
+                elements.push(element);
+            }
+            Event::End(_) => {
+                if elements.pop().as_deref() == Some(b"track") {
+                    entries.extend(track.take());
+                }
+            }
+            Event::Text(content) => {
+                let content = content.unescape().map_err(invalid)?;
+                let value = non_empty(&content);
+                let parent = elements.len().checked_sub(2).map(|i| &elements[i][..]);
+                let element = elements.last().map(|e| &e[..]);
+
+                match (&mut track, parent, element) {
+                    (None, Some(b"playlist"), Some(b"title")) => name = value,
+                    (Some(entry), Some(b"track"), Some(b"location")) => {
+                        entry.location = content.trim().to_string()
+                    }
+                    (Some(entry), Some(b"track"), Some(b"title")) => entry.title = value,
+                    (Some(entry), Some(b"track"), Some(b"creator")) => entry.artist = value,
+                    (Some(entry), Some(b"track"), Some(b"album")) => entry.album = value,
+                    (Some(entry), Some(b"track"), Some(b"duration")) => {
+                        entry.duration = content.trim().parse().ok().map(Duration::from_millis)
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let entries = entries
+        .into_iter()
+        .filter(|entry| !entry.location.is_empty())
+        .collect();
+    Ok((name, entries))
+}
+
+fn write_xspf(playlist: &Playlist, base: &Path) -> Result<String, SoursError> {
+    let base_url = Url::from_directory_path(base).ok();
+
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    text.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    text.push_str(&format!("  <title>{}</title>\n", escape(&playlist.name)));
+    text.push_str("  <trackList>\n");
+
+    for resource in &playlist.resources {
+        let path = absolute(&resource.path)?;
+        let Ok(url) = Url::from_file_path(&path) else {
+            continue;
+        };
+        // Resources inside the playlist's directory get a relative URI
+        let location = match (&base_url, path.starts_with(base)) {
+            (Some(base_url), true) => base_url.make_relative(&url),
+            _ => None,
+        };
+        let location = location.unwrap_or_else(|| url.to_string());
+
+        let metadata = &resource.metadata;
+        text.push_str("    <track>\n");
+        text.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape(&location)
+        ));
+        let title = metadata
+            .title
+            .clone()
+            .unwrap_or_else(|| resource.display_name());
+        text.push_str(&format!("      <title>{}</title>\n", escape(&title)));
+        if let Some(artist) = &metadata.artist {
+            text.push_str(&format!("      <creator>{}</creator>\n", escape(artist)));
+        }
+        if let Some(album) = &metadata.album {
+            text.push_str(&format!("      <album>{}</album>\n", escape(album)));
+        }
+        if !resource.duration.is_zero() {
+            let millis = resource.duration.as_millis();
+            text.push_str(&format!("      <duration>{}</duration>\n", millis));
+        }
+        text.push_str("    </track>\n");
+    }
+
+    text.push_str("  </trackList>\n</playlist>\n");
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sours-playlist-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn playlist(dir: &Path) -> Playlist {
+        let mut playlist = Playlist::new("Mix & <Match>");
+        let mut first = AudioResource::new(dir.join("music/a b.mp3"), Duration::from_secs(61));
+        first.metadata.artist = Some("Artist".to_string());
+        first.metadata.title = Some("Title".to_string());
+        playlist.resources.push(first);
+        playlist.resources.push(AudioResource::new(
+            PathBuf::from("/elsewhere/c.flac"),
+            Duration::ZERO,
+        ));
+        playlist
+    }
+
+    #[test]
+    fn lengths_that_are_no_duration_are_unknown() {
+        assert_eq!(parse_seconds(" 12.5 "), Some(Duration::from_millis(12500)));
+        for text in ["-1", "0", "inf", "-inf", "NaN", "1e999", "", "x"] {
+            assert_eq!(parse_seconds(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn m3u_entries_take_the_extinf_before_them() {
+        let text = "#EXTM3U
+#PLAYLIST: Mix
+# a comment
+#EXTINF:123 tvg-id=\"a,b\" group-title=\"x\",Artist - Title
+music/a.mp3
+
+#EXTINF:inf,Stream
+http://example.com/stream
+#EXTINF:-1,Only Title
+#EXTVLCOPT:network-caching=1000
+/abs/c.mp3
+/abs/d.mp3
+";
+        let (name, entries) = parse_m3u(text);
+        assert_eq!(name.as_deref(), Some("Mix"));
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0].location, "music/a.mp3");
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(123)));
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(entries[0].title.as_deref(), Some("Title"));
+        assert_eq!(entries[1].location, "http://example.com/stream");
+        assert_eq!(entries[1].duration, None);
+        assert_eq!(entries[2].location, "/abs/c.mp3");
+        assert_eq!(entries[2].title.as_deref(), Some("Only Title"));
+        assert_eq!(entries[2].artist, None);
+        assert_eq!(entries[3].location, "/abs/d.mp3");
+        assert_eq!(entries[3].title, None);
+    }
+
+    #[test]
+    fn pls_entries_are_collected_by_number() {
+        let text = "[playlist]
+title2=Second
+LENGTH1=1e999
+Title1=Artist - First
+File2=b.mp3
+NumberOfEntries=3
+file1 = a.mp3
+Title3=No File
+Length3=3
+Version=2
+";
+        let entries = parse_pls(text);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].location, "a.mp3");
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(entries[0].title.as_deref(), Some("First"));
+        assert_eq!(entries[0].duration, None);
+        assert_eq!(entries[1].location, "b.mp3");
+        assert_eq!(entries[1].title.as_deref(), Some("Second"));
+    }
+
+    #[test]
+    fn xspf_tracks_are_unescaped() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Rock &amp; Roll</title>
+  <trackList>
+    <track>
+      <location>music/a%20b.mp3</location>
+      <title>&lt;Live&gt; &amp; Loud</title>
+      <creator>Artist</creator>
+      <album>Album</album>
+      <duration>61500</duration>
+      <extension application="x"><title>Ignored</title></extension>
+    </track>
+    <track>
+      <location>file:///abs/c.flac</location>
+    </track>
+    <track>
+      <title>No Location</title>
+    </track>
+  </trackList>
+</playlist>"#;
+        let (name, entries) = parse_xspf(text).unwrap();
+        assert_eq!(name.as_deref(), Some("Rock & Roll"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].location, "music/a%20b.mp3");
+        assert_eq!(entries[0].title.as_deref(), Some("<Live> & Loud"));
+        assert_eq!(entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(entries[0].album.as_deref(), Some("Album"));
+        assert_eq!(entries[0].duration, Some(Duration::from_millis(61500)));
+        assert_eq!(entries[1].location, "file:///abs/c.flac");
+
+        assert!(parse_xspf("<playlist><title>x</playlist>").is_err());
+    }
+
+    #[test]
+    fn locations_resolve_against_the_playlist_directory() {
+        let base = Path::new("/base/lists");
+        assert_eq!(
+            resolve_path("music/a.mp3", base),
+            Some(PathBuf::from("/base/lists/music/a.mp3"))
+        );
+        assert_eq!(
+            resolve_path("/abs/a.mp3", base),
+            Some(PathBuf::from("/abs/a.mp3"))
+        );
+        assert_eq!(
+            resolve_path("file:///abs/a%20b.mp3", base),
+            Some(PathBuf::from("/abs/a b.mp3"))
+        );
+        assert_eq!(resolve_path("http://example.com/stream", base), None);
+
+        assert_eq!(
+            resolve_uri("music/a%20b.mp3", base),
+            Some(PathBuf::from("/base/lists/music/a b.mp3"))
+        );
+        assert_eq!(
+            resolve_uri("../a.mp3", base),
+            Some(PathBuf::from("/base/a.mp3"))
+        );
+        assert_eq!(
+            resolve_uri("file:///abs/a.mp3", base),
+            Some(PathBuf::from("/abs/a.mp3"))
+        );
+        assert_eq!(resolve_uri("http://example.com/stream", base), None);
+    }
+
+    #[test]
+    fn exported_playlists_import_with_their_paths_relative() {
+        for format in PlaylistFormat::ALL {
+            let name = format.extensions()[0];
+            let dir = temp_dir(name);
+            let file = dir.join(format!("list.{}", name));
+            export_playlist(&playlist(&dir), &file).unwrap();
+
+            let text = std::fs::read_to_string(&file).unwrap();
+            assert!(!text.contains(&*dir.to_string_lossy()), "{}", text);
+
+            // Moved together with its files, the playlist still finds them
+            let moved = temp_dir(&format!("{}-moved", name));
+            std::fs::remove_dir(&moved).unwrap();
+            std::fs::rename(&dir, &moved).unwrap();
+            let imported = import_playlist(&moved.join(format!("list.{}", name))).unwrap();
+            std::fs::remove_dir_all(&moved).unwrap();
+
+            let paths: Vec<_> = imported.resources.iter().map(|r| r.path.clone()).collect();
+            assert_eq!(
+                paths,
+                [
+                    moved.join("music/a b.mp3"),
+                    PathBuf::from("/elsewhere/c.flac")
+                ],
+                "{}",
+                text
+            );
+            let first = &imported.resources[0];
+            assert_eq!(first.metadata.artist.as_deref(), Some("Artist"));
+            assert_eq!(first.metadata.title.as_deref(), Some("Title"));
+            assert_eq!(first.duration, Duration::from_secs(61));
+            if format != PlaylistFormat::Pls {
+                assert_eq!(imported.name, "Mix & <Match>");
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use rodio::cpal::traits::HostTrait;
+use rodio::{DeviceTrait, OutputStream, OutputStreamHandle, StreamError};
+
+/// The output devices of the default audio host at one point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Devices {
+    pub names: Vec<String>,
+    /// The system's default device, `None` if there is none
+    pub default: Option<String>,
+}
+
+impl Devices {
+    /// Ask the audio host, this can block for a while on some hosts
+    pub fn list() -> Self {
+        Self {
+            names: output_device_names(),
+            default: default_device_name(),
+        }
+    }
+
+    pub fn available(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+}
+
+/// Lists the output devices on its own thread, so the ui doesn't wait for the audio host
+pub struct DeviceWatcher {
+    receiver: Receiver<Devices>,
+}
+
+impl DeviceWatcher {
+    /// Checks every `interval`, `wake` is called from the watching thread whenever
+    /// the devices changed, use it to request a repaint
+    pub fn new(interval: Duration, wake: impl Fn() + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut last = None;
+            loop {
+                let devices = Devices::list();
+                if last.as_ref() != Some(&devices) {
+                    last = Some(devices.clone());
+                    // The watcher was dropped
+                    if sender.send(devices).is_err() {
+                        return;
+                    }
+                    wake();
+                }
+                thread::sleep(interval);
+            }
+        });
+        Self { receiver }
+    }
+    pub fn poll(&self) -> Option<Devices> {
+        /*
+            The devices if they changed since the last poll
+        */
+        self.receiver.try_iter().last()
+    }
+}
+
+/// Names of all output devices of the default audio host
+pub fn output_device_names() -> Vec<String> {
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn device_available(name: &str) -> bool {
+    output_device_names().iter().any(|n| n == name)
+}
+
+/// Name of the system's default output device
+pub fn default_device_name() -> Option<String> {
+    rodio::cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok())
+}
+
+/// Opens a stream on the output device called `name`, or on the
+/// system's default device if `name` is `None`
+pub fn open_output(name: Option<&str>) -> Result<(OutputStream, OutputStreamHandle), StreamError> {
+    let Some(name) = name else {
+        return OutputStream::try_default();
+    };
+
+    let device = rodio::cpal::default_host()
+        .output_devices()
+        .ok()
+        .and_then(|mut devices| devices.find(|d| d.name().ok().as_deref() == Some(name)))
+        .ok_or(StreamError::NoDevice)?;
+
+    OutputStream::try_from_device(&device)
+}
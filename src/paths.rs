@@ -0,0 +1,293 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde_json::Value;
+
+/// A file with this name in the working directory, or next to the executable,
+/// keeps every file of sours in that directory like older versions did
+pub const PORTABLE_MARKER: &str = "sours.portable";
+
+const CONFIG_FILE: &str = "sours.json";
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// Where sours keeps its files
+#[derive(Debug, Clone)]
+pub struct Paths {
+    /// The options file
+    pub config: PathBuf,
+    /// Directory for files that can be recreated, like cover thumbnails
+    pub cache: PathBuf,
+    /// Directory yt-dlp downloads into
+    pub downloads: PathBuf,
+    /// The yt-dlp executable
+    pub yt_dlp: PathBuf,
+    /// Whether these are the old paths relative to one directory
+    pub portable: bool,
+}
+
+impl Paths {
+    /// Portable paths if a `PORTABLE_MARKER` is found, otherwise the per-user directories:
+    /// the XDG base directories on Linux, AppData on Windows, Library on macOS.
+    /// `config` replaces the options file, e.g. from `--config`
+    pub fn detect(config: Option<PathBuf>) -> Self {
+        let mut paths = match Self::portable_root() {
+            Some(root) => Self::portable(&root),
+            None => Self::per_user().unwrap_or_else(|| Self::portable(Path::new("."))),
+        };
+        if let Some(config) = config {
+            paths.config = config;
+        }
+        paths
+    }
+
+    /// Every file in `root`, like older versions kept them in the working directory
+    pub fn portable(root: &Path) -> Self {
+        Self {
+            config: root.join(CONFIG_FILE),
+            cache: root.join("cache"),
+            downloads: root.join("downloads"),
+            yt_dlp: Self::shipped_yt_dlp(root).unwrap_or_else(|| PathBuf::from("yt-dlp")),
+            portable: true,
+        }
+    }
+
+    /// `None` if the OS has no home directory to put them in
+    pub fn per_user() -> Option<Self> {
+        Some(Self {
+            config: dirs::config_dir()?.join("sours").join(CONFIG_FILE),
+            cache: dirs::cache_dir()?.join("sours"),
+            // Downloads are managed by sours, so they live with its data, not in the user's Downloads
+            downloads: dirs::data_local_dir()?.join("sours").join("downloads"),
+            yt_dlp: exe_dir()
+                .and_then(|dir| Self::shipped_yt_dlp(&dir))
+                .unwrap_or_else(|| PathBuf::from("yt-dlp")),
+            portable: false,
+        })
+    }
+
+    /// Create the directories, so they can be read from and written into right away
+    pub fn create_dirs(&self) -> io::Result<()> {
+        if let Some(parent) = self.config.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::create_dir_all(&self.cache)?;
+        std::fs::create_dir_all(&self.downloads)
+    }
+
+    fn portable_root() -> Option<PathBuf> {
+        [Some(PathBuf::from(".")), exe_dir()]
+            .into_iter()
+            .flatten()
+            .find(|dir| dir.join(PORTABLE_MARKER).exists())
+    }
+
+    /// yt-dlp ships in `bin` next to sours on Windows, elsewhere it's taken from the `PATH`
+    fn shipped_yt_dlp(dir: &Path) -> Option<PathBuf> {
+        let shipped = dir.join("bin").join("yt-dlp.exe");
+        (cfg!(windows) && shipped.exists()).then_some(shipped)
+    }
+
+    /// Move the options and downloads older versions kept in the working directory
+    /// to these paths, once. Returns whether there was anything to move
+    pub fn migrate_from_working_dir(&self) -> io::Result<bool> {
+        self.migrate_from(Path::new("."))
+    }
+
+    fn migrate_from(&self, root: &Path) -> io::Result<bool> {
+        /*
+            Only if there are no options here yet, so it happens on the first start only.
+            The downloads are moved as they can be large, the old options are copied
+            with the downloads' paths pointing to where they were moved
+        */
+        let old = Self::portable(root);
+        if self.portable || self.config.exists() || !old.config.exists() {
+            return Ok(false);
+        }
+
+        if let Ok(downloaded) = std::fs::read_dir(&old.downloads) {
+            std::fs::create_dir_all(&self.downloads)?;
+            for entry in downloaded {
+                let entry = entry?;
+                let to = self.downloads.join(entry.file_name());
+                // Renaming fails across file systems, copy those instead
+                if std::fs::rename(entry.path(), &to).is_err() {
+                    copy_and_remove(&entry.path(), &to)?;
+                }
+            }
+        }
+
+        // Options that can't be parsed are copied as they are, loading them reports why
+        let json = std::fs::read_to_string(&old.config)?;
+        let json = match serde_json::from_str::<Value>(&json) {
+            Ok(mut options) => {
+                let from = std::env::current_dir()?.join(&old.downloads);
+                relocate(&mut options, &from, &self.downloads);
+                serde_json::to_string_pretty(&options)?
+            }
+            Err(_) => json,
+        };
+        if let Some(parent) = self.config.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.config, json)?;
+        Ok(true)
+    }
+}
+
+/// Copy the file or directory `from` to `to`, then remove it
+fn copy_and_remove(from: &Path, to: &Path) -> io::Result<()> {
+    if from.symlink_metadata()?.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_and_remove(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        std::fs::remove_dir(from)
+    } else {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    exe.parent().map(Path::to_path_buf)
+}
+
+/// Point every path in `json` that's inside `from` to the same file inside `to`
+fn relocate(json: &mut Value, from: &Path, to: &Path) {
+    match json {
+        Value::String(text) => {
+            let Ok(path) = std::env::current_dir().map(|cwd| cwd.join(&*text)) else {
+                return;
+            };
+            if let Ok(file) = path.strip_prefix(from) {
+                *text = to.join(file).to_string_lossy().to_string();
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| relocate(v, from, to)),
+        Value::Object(values) => values.values_mut().for_each(|v| relocate(v, from, to)),
+        _ => {}
+    }
+}
+
+/// Use `paths` for the rest of the process, ignored once `get` was called
+pub fn init(paths: Paths) {
+    let _ = PATHS.set(paths);
+}
+
+/// The paths set by `init`, or the detected ones
+pub fn get() -> &'static Paths {
+    PATHS.get_or_init(|| Paths::detect(None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sours-paths-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn per_user(root: &Path) -> Paths {
+        Paths {
+            config: root.join("config").join(CONFIG_FILE),
+            cache: root.join("cache"),
+            downloads: root.join("data").join("downloads"),
+            yt_dlp: PathBuf::from("yt-dlp"),
+            portable: false,
+        }
+    }
+
+    #[test]
+    fn paths_inside_the_old_directory_are_relocated() {
+        let cwd = std::env::current_dir().unwrap();
+        let from = cwd.join("downloads");
+        let to = Path::new("/new/downloads");
+        let mut options = json!({
+            "volume": 50,
+            "playlists": [{"resources": [
+                from.join("a.mp3").to_string_lossy(),
+                {"path": from.join("sub/b.mp3").to_string_lossy()},
+                "downloads/c.mp3",
+                "/elsewhere/d.mp3",
+            ]}],
+        });
+        relocate(&mut options, &from, to);
+
+        assert_eq!(options["volume"], json!(50));
+        let resources = &options["playlists"][0]["resources"];
+        assert_eq!(resources[0], json!("/new/downloads/a.mp3"));
+        assert_eq!(resources[1]["path"], json!("/new/downloads/sub/b.mp3"));
+        assert_eq!(resources[2], json!("/new/downloads/c.mp3"));
+        assert_eq!(resources[3], json!("/elsewhere/d.mp3"));
+    }
+
+    #[test]
+    fn old_downloads_and_options_are_moved_once() {
+        let old = temp_dir("old");
+        let new = temp_dir("new");
+        let paths = per_user(&new);
+
+        std::fs::create_dir_all(old.join("downloads/Channel")).unwrap();
+        std::fs::write(old.join("downloads/a.mp3"), b"a").unwrap();
+        std::fs::write(old.join("downloads/Channel/b.mp3"), b"b").unwrap();
+        let resource = old.join("downloads/Channel/b.mp3");
+        let options = json!({"playlists": [{"resources": [resource.to_string_lossy()]}]});
+        std::fs::write(old.join(CONFIG_FILE), options.to_string()).unwrap();
+
+        assert!(paths.migrate_from(&old).unwrap());
+        assert_eq!(std::fs::read(paths.downloads.join("a.mp3")).unwrap(), b"a");
+        let moved = paths.downloads.join("Channel/b.mp3");
+        assert_eq!(std::fs::read(&moved).unwrap(), b"b");
+        assert!(!old.join("downloads/a.mp3").exists());
+
+        let options: Value =
+            serde_json::from_str(&std::fs::read_to_string(&paths.config).unwrap()).unwrap();
+        let resources = &options["playlists"][0]["resources"];
+        assert_eq!(resources[0], json!(moved.to_string_lossy()));
+
+        // The options are there now, so nothing is moved again
+        std::fs::write(old.join("downloads/c.mp3"), b"c").unwrap();
+        assert!(!paths.migrate_from(&old).unwrap());
+        assert!(old.join("downloads/c.mp3").exists());
+
+        std::fs::remove_dir_all(&old).unwrap();
+        std::fs::remove_dir_all(&new).unwrap();
+    }
+
+    #[test]
+    fn portable_paths_are_not_migrated() {
+        let old = temp_dir("portable-old");
+        std::fs::write(old.join(CONFIG_FILE), "{}").unwrap();
+        let new = temp_dir("portable-new");
+        let paths = Paths::portable(&new);
+
+        assert!(!paths.migrate_from(&old).unwrap());
+        assert!(!paths.config.exists());
+        std::fs::remove_dir_all(&old).unwrap();
+        std::fs::remove_dir_all(&new).unwrap();
+    }
+
+    #[test]
+    fn directories_are_copied_with_everything_in_them() {
+        let dir = temp_dir("copy");
+        std::fs::create_dir_all(dir.join("from/a/b")).unwrap();
+        std::fs::write(dir.join("from/1.mp3"), b"1").unwrap();
+        std::fs::write(dir.join("from/a/b/2.mp3"), b"2").unwrap();
+
+        copy_and_remove(&dir.join("from"), &dir.join("to")).unwrap();
+        assert!(!dir.join("from").exists());
+        assert_eq!(std::fs::read(dir.join("to/1.mp3")).unwrap(), b"1");
+        assert_eq!(std::fs::read(dir.join("to/a/b/2.mp3")).unwrap(), b"2");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
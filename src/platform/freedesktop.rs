@@ -0,0 +1,91 @@
+use std::{
+    io,
+    os::unix::process::CommandExt,
+    path::Path,
+    process::{Child, Command},
+    thread,
+};
+
+use url::Url;
+
+use super::Platform;
+
+/// Linux and other desktops following the freedesktop.org specifications
+pub struct Freedesktop;
+
+impl Freedesktop {
+    fn file_uri(path: &Path) -> io::Result<String> {
+        // Percent-encoded, file managers don't take spaces or `#` in URIs verbatim
+        let path = path.canonicalize()?;
+        Url::from_file_path(&path)
+            .map(String::from)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "not an absolute path"))
+    }
+}
+
+impl Platform for Freedesktop {
+    fn open(&self, path: &Path) -> io::Result<()> {
+        // Waited for in the background, so it doesn't linger as a zombie once it exited
+        let mut child = Command::new("xdg-open").arg(path).spawn()?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+    fn reveal(&self, path: &Path) -> io::Result<()> {
+        /*
+            Ask the file manager over D-Bus to select the file,
+            if none implements `FileManager1` just open the containing directory
+        */
+        let uri = Self::file_uri(path)?;
+        let shown = zbus::blocking::Connection::session().and_then(|connection| {
+            connection.call_method(
+                Some("org.freedesktop.FileManager1"),
+                "/org/freedesktop/FileManager1",
+                Some("org.freedesktop.FileManager1"),
+                "ShowItems",
+                &(vec![uri], ""),
+            )
+        });
+
+        match shown {
+            Ok(_) => Ok(()),
+            Err(_) => match path.parent() {
+                Some(parent) => self.open(parent),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no parent directory",
+                )),
+            },
+        }
+    }
+    fn own_process_group(&self, command: &mut Command) {
+        command.process_group(0);
+    }
+    fn kill_tree(&self, child: &mut Child) -> io::Result<()> {
+        // The group has the id of its leader, a negative id signals the whole group
+        let group = child.id() as libc::pid_t;
+        match unsafe { libc::kill(-group, libc::SIGKILL) } {
+            0 => Ok(()),
+            _ => child.kill(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uris_are_percent_encoded() {
+        let dir = std::env::temp_dir().join(format!("sours {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("#1 100%.mp3");
+        std::fs::write(&path, b"").unwrap();
+
+        let uri = Freedesktop::file_uri(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let uri = uri.unwrap();
+        assert!(uri.starts_with("file:///"), "{}", uri);
+        assert!(uri.ends_with("/%231%20100%25.mp3"), "{}", uri);
+        assert!(!uri.contains(' '), "{}", uri);
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    ffi::c_void,
+    io,
+    path::Path,
+    process::{Child, Command},
+};
+
+#[cfg(not(windows))]
+mod freedesktop;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(not(windows))]
+pub use self::freedesktop::Freedesktop as Native;
+#[cfg(windows)]
+pub use self::windows::Windows as Native;
+
+/// What sours needs from the desktop it runs on, beyond what eframe provides
+pub trait Platform {
+    /// Opens `path` with the application the OS associates with it
+    fn open(&self, path: &Path) -> io::Result<()>;
+    /// Shows `path` selected in the file manager
+    fn reveal(&self, path: &Path) -> io::Result<()>;
+    /// The raw handle of the window titled `title`, for APIs that need one
+    fn window_handle(&self, _title: &str) -> Option<*mut c_void> {
+        None
+    }
+    /// Keeps `command` from opening a console window when spawned
+    fn hide_console(&self, _command: &mut Command) {}
+    /// Makes the process `command` spawns the leader of the processes it starts,
+    /// so `kill_tree` reaches them too
+    fn own_process_group(&self, _command: &mut Command) {}
+    /// Kills `child` and the processes it started, like the ffmpeg yt-dlp runs
+    fn kill_tree(&self, child: &mut Child) -> io::Result<()> {
+        child.kill()
+    }
+}
+
+/// The implementation for the OS sours was built for
+pub fn native() -> Native {
+    Native
+}
@@ -0,0 +1,92 @@
+use std::{
+    ffi::c_void,
+    io,
+    os::windows::process::CommandExt,
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::{
+        Foundation::HWND,
+        System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE},
+        UI::{
+            Shell::ShellExecuteW,
+            WindowsAndMessaging::{FindWindowW, SW_SHOWNORMAL},
+        },
+    },
+};
+
+use super::Platform;
+
+/// Spawn console programs like yt-dlp without flashing a console window
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+pub struct Windows;
+
+impl Windows {
+    fn display_path(path: &Path) -> io::Result<String> {
+        /*
+            Canonical paths carry the `\\?\` prefix, which explorer doesn't understand
+        */
+        let path = path.canonicalize()?;
+        Ok(path.to_string_lossy().replace("\\\\?\\", ""))
+    }
+}
+
+impl Platform for Windows {
+    fn open(&self, path: &Path) -> io::Result<()> {
+        let path = Self::display_path(path)?;
+        let result = unsafe {
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE);
+            ShellExecuteW(
+                HWND::default(),
+                PCWSTR::null(),
+                &HSTRING::from(path),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            )
+        };
+
+        // Anything up to 32 is an error code instead of an instance handle
+        if result.0 <= 32 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    fn reveal(&self, path: &Path) -> io::Result<()> {
+        // explorer only understands `/select,"<path>"` verbatim, so skip Rust's quoting
+        let path = Self::display_path(path)?;
+        Command::new("explorer.exe")
+            .raw_arg(format!("/select,\"{}\"", path))
+            .spawn()?;
+        Ok(())
+    }
+    fn window_handle(&self, title: &str) -> Option<*mut c_void> {
+        let hwnd = unsafe { FindWindowW(PCWSTR::null(), &HSTRING::from(title)) };
+        if hwnd.0 == 0 {
+            return None;
+        }
+        Some(hwnd.0 as *mut c_void)
+    }
+    fn hide_console(&self, command: &mut Command) {
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    fn kill_tree(&self, child: &mut Child) -> io::Result<()> {
+        // Windows has no process groups to signal, taskkill walks the children instead
+        let mut command = Command::new("taskkill");
+        self.hide_console(&mut command);
+        let status = command
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        match status.success() {
+            true => Ok(()),
+            false => child.kill(),
+        }
+    }
+}
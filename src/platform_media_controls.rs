@@ -0,0 +1,215 @@
+use std::{
+    fmt::Debug,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use souvlaki::{
+    MediaControlEvent, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection,
+};
+use url::Url;
+
+use crate::audio_player::{AudioPlayer, PlayerAction};
+use crate::audio_resource::AudioResource;
+
+/// How far the `Seek` event without an offset skips, like the arrow keys in the window
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+/// What was last published, to only send changes to the OS
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Published {
+    Stopped,
+    Paused(Duration),
+    Playing(Duration, Instant),
+}
+
+/// The OS's now-playing integration (MPRIS on Linux, SMTC on Windows, Now Playing on macOS)
+pub struct MediaControls {
+    controls: Option<souvlaki::MediaControls>,
+    receiver: Receiver<PlayerAction>,
+    resource: Option<AudioResource>,
+    playback: Option<Published>,
+    volume: Option<u8>,
+}
+
+impl MediaControls {
+    /// `wake` is called on every incoming event, use it to request a repaint.
+    /// `hwnd` is the window handle, which is only needed (and required) on Windows
+    pub fn new(
+        hwnd: Option<*mut std::ffi::c_void>,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let config = PlatformConfig {
+            dbus_name: "sours",
+            display_name: "sours",
+            hwnd,
+        };
+
+        // Without a session bus (or window on Windows) sours simply runs without media controls
+        let controls = souvlaki::MediaControls::new(config)
+            .ok()
+            .and_then(|mut controls| {
+                controls
+                    .attach(move |event| {
+                        if let Some(action) = action_from_event(event) {
+                            let _ = sender.send(action);
+                            wake();
+                        }
+                    })
+                    .ok()?;
+                Some(controls)
+            });
+
+        Self {
+            controls,
+            receiver,
+            resource: None,
+            playback: None,
+            volume: None,
+        }
+    }
+    pub fn available(&self) -> bool {
+        self.controls.is_some()
+    }
+    pub fn poll(&self) -> Vec<PlayerAction> {
+        /*
+            Collect the actions requested by the OS since the last poll
+        */
+        self.receiver.try_iter().collect()
+    }
+    pub fn update(&mut self, player: &AudioPlayer, volume: u8) {
+        /*
+            Publish what changed since the last update, called every frame
+        */
+        let Some(controls) = &mut self.controls else {
+            return;
+        };
+
+        if self.resource != player.current {
+            self.resource = player.current.clone();
+            let _ = match &self.resource {
+                Some(resource) => {
+                    let title = resource.display_name();
+                    let cover_url = resource
+                        .cover_art()
+                        .and_then(|path| path.canonicalize().ok())
+                        .and_then(|path| Url::from_file_path(path).ok())
+                        .map(String::from);
+                    let metadata = &resource.metadata;
+
+                    controls.set_metadata(MediaMetadata {
+                        title: Some(&title),
+                        album: metadata.album.as_deref(),
+                        artist: metadata.artist.as_deref(),
+                        cover_url: cover_url.as_deref(),
+                        duration: Some(resource.duration),
+                    })
+                }
+                None => controls.set_metadata(MediaMetadata::default()),
+            };
+        }
+
+        let playback = match player.position() {
+            Some(position) if player.is_playing() => {
+                Published::Playing(position.elapsed, Instant::now())
+            }
+            Some(position) => Published::Paused(position.elapsed),
+            None => Published::Stopped,
+        };
+        if Self::playback_changed(self.playback, playback) {
+            self.playback = Some(playback);
+            let _ = controls.set_playback(match playback {
+                Published::Stopped => MediaPlayback::Stopped,
+                Published::Paused(elapsed) => MediaPlayback::Paused {
+                    progress: Some(MediaPosition(elapsed)),
+                },
+                Published::Playing(elapsed, _) => MediaPlayback::Playing {
+                    progress: Some(MediaPosition(elapsed)),
+                },
+            });
+        }
+
+        #[cfg(target_os = "linux")]
+        if self.volume != Some(volume) {
+            self.volume = Some(volume);
+            let _ = controls.set_volume(volume as f64 / 100.0);
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.volume = Some(volume);
+        }
+    }
+    fn playback_changed(before: Option<Published>, now: Published) -> bool {
+        /*
+            Clients advance the position of a playing track on their own,
+            so it only has to be sent again if it jumped, e.g. after a seek
+        */
+        match (before, now) {
+            (Some(Published::Playing(before, at)), Published::Playing(elapsed, _)) => {
+                let expected = before + at.elapsed();
+                let drift = expected.max(elapsed) - expected.min(elapsed);
+                drift > Duration::from_secs(1)
+            }
+            (before, now) => before != Some(now),
+        }
+    }
+}
+
+/// The `PlayerAction` an OS media event asks for, `None` for events sours doesn't handle
+fn action_from_event(event: MediaControlEvent) -> Option<PlayerAction> {
+    let skip = |direction: SeekDirection, by: Duration| match direction {
+        SeekDirection::Forward => PlayerAction::Forward(by),
+        SeekDirection::Backward => PlayerAction::Back(by),
+    };
+
+    match event {
+        MediaControlEvent::Play => Some(PlayerAction::Play),
+        MediaControlEvent::Pause => Some(PlayerAction::Pause),
+        MediaControlEvent::Toggle => Some(PlayerAction::PlayPause),
+        MediaControlEvent::Stop => Some(PlayerAction::Stop),
+        MediaControlEvent::Next => Some(PlayerAction::Next),
+        MediaControlEvent::Previous => Some(PlayerAction::Previous),
+        MediaControlEvent::Seek(direction) => Some(skip(direction, SEEK_STEP)),
+        MediaControlEvent::SeekBy(direction, offset) => Some(skip(direction, offset)),
+        MediaControlEvent::SetPosition(MediaPosition(position)) => {
+            Some(PlayerAction::Seek(position))
+        }
+        MediaControlEvent::SetVolume(volume) => Some(PlayerAction::SetVolume(
+            (volume.clamp(0.0, 1.0) * 100.0).round() as u8,
+        )),
+        MediaControlEvent::OpenUri(_) | MediaControlEvent::Raise | MediaControlEvent::Quit => None,
+    }
+}
+
+impl Debug for MediaControls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MediaControls")
+            .field("available", &self.available())
+            .field("resource", &self.resource)
+            .field("playback", &self.playback)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeks_keep_their_full_offset() {
+        let by = Duration::from_millis(500);
+        assert_eq!(
+            action_from_event(MediaControlEvent::SeekBy(SeekDirection::Backward, by)),
+            Some(PlayerAction::Back(by))
+        );
+        assert_eq!(
+            action_from_event(MediaControlEvent::SeekBy(SeekDirection::Forward, by)),
+            Some(PlayerAction::Forward(by))
+        );
+        assert_eq!(
+            action_from_event(MediaControlEvent::Seek(SeekDirection::Forward)),
+            Some(PlayerAction::Forward(SEEK_STEP))
+        );
+    }
+}
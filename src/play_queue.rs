@@ -0,0 +1,392 @@
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::audio_resource::AudioResource;
+
+/// How many played resources are remembered for `Previous`
+const MAX_HISTORY: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RepeatMode {
+    /// Stop after the last resource of the playlist
+    Off,
+    /// Start over once the playlist is through
+    #[default]
+    All,
+    /// Play the current resource again and again
+    One,
+}
+
+/// A shuffled play order that doesn't touch the playlist itself.
+/// Every resource is played once before any is played again
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Shuffle {
+    seed: u64,
+    // Counts the times the playlist was played through, to vary the order
+    round: u64,
+    played: Vec<AudioResource>,
+}
+
+impl Shuffle {
+    fn pick(
+        &self,
+        current: Option<&AudioResource>,
+        playlist: &[AudioResource],
+    ) -> Option<AudioResource> {
+        /*
+            Pick the next resource from those not played yet this round.
+            The pick only depends on the state, so it stays the same until something plays
+        */
+        let unplayed: Vec<&AudioResource> = playlist
+            .iter()
+            .filter(|r| !self.played.contains(r) && Some(*r) != current)
+            .collect();
+
+        let mixed = self.seed ^ self.round.rotate_left(32) ^ self.played.len() as u64;
+        let mut rng = StdRng::seed_from_u64(mixed);
+        unplayed.choose(&mut rng).map(|r| (*r).clone())
+    }
+
+    fn next_round(
+        &self,
+        current: Option<&AudioResource>,
+        playlist: &[AudioResource],
+    ) -> Option<AudioResource> {
+        let next = Shuffle {
+            seed: self.seed,
+            round: self.round + 1,
+            played: Vec::new(),
+        };
+        next.pick(current, playlist)
+    }
+}
+
+/// What plays next: resources queued by the user first, then the playlist continues
+/// after the playing resource, in order or shuffled. Also remembers what was played, for `Previous`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlayQueue {
+    queue: VecDeque<AudioResource>,
+    // Most recently played last
+    history: Vec<AudioResource>,
+    #[serde(default)]
+    repeat: RepeatMode,
+    #[serde(default)]
+    shuffle: Option<Shuffle>,
+}
+
+impl PlayQueue {
+    /// Queue `resource` to play right after the playing one
+    pub fn play_next(&mut self, resource: AudioResource) {
+        self.queue.push_front(resource);
+    }
+
+    /// Queue `resource` behind everything already queued
+    pub fn add(&mut self, resource: AudioResource) {
+        self.queue.push_back(resource);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.queue.remove(index);
+    }
+
+    /// Forget `resource` entirely, e.g. when it's removed from the playlist
+    pub fn remove_resource(&mut self, resource: &AudioResource) {
+        self.queue.retain(|r| r != resource);
+        self.history.retain(|r| r != resource);
+        if let Some(shuffle) = &mut self.shuffle {
+            shuffle.played.retain(|r| r != resource);
+        }
+    }
+
+    /// Move the queued resource at `from` to `to`
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if from >= self.queue.len() || to >= self.queue.len() {
+            return;
+        }
+        if let Some(resource) = self.queue.remove(from) {
+            self.queue.insert(to, resource);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    pub fn queued(&self) -> impl Iterator<Item = &AudioResource> {
+        self.queue.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    pub fn shuffled(&self) -> bool {
+        self.shuffle.is_some()
+    }
+
+    /// Turn shuffling on with a random order, or off
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        match shuffle {
+            true => self.set_shuffle_seed(rand::thread_rng().gen()),
+            false => self.shuffle = None,
+        }
+    }
+
+    /// Turn shuffling on with the order given by `seed`, the same seed
+    /// and playlist always give the same order
+    pub fn set_shuffle_seed(&mut self, seed: u64) {
+        self.shuffle = Some(Shuffle {
+            seed,
+            ..Default::default()
+        });
+    }
+
+    /// The resource autoplay continues with after `current`, without consuming it.
+    /// Follows the repeat mode, so with `RepeatMode::One` that's `current` again
+    pub fn upcoming(
+        &self,
+        current: Option<&AudioResource>,
+        playlist: &[AudioResource],
+    ) -> Option<AudioResource> {
+        if let (RepeatMode::One, Some(current)) = (self.repeat, current) {
+            return Some(current.clone());
+        }
+        self.skip_target(current, playlist)
+    }
+
+    /// The resource `Next` skips to from `current`: the first queued one,
+    /// or else the next one in the playlist's (possibly shuffled) order.
+    /// If `current` isn't in the playlist, that order starts from the beginning
+    pub fn skip_target(
+        &self,
+        current: Option<&AudioResource>,
+        playlist: &[AudioResource],
+    ) -> Option<AudioResource> {
+        if let Some(queued) = self.queue.front() {
+            return Some(queued.clone());
+        }
+
+        // Only repeating stops the playlist from ending
+        let wrap = self.repeat != RepeatMode::Off;
+
+        if let Some(shuffle) = &self.shuffle {
+            return match shuffle.pick(current, playlist) {
+                Some(next) => Some(next),
+                None if wrap => shuffle.next_round(current, playlist),
+                None => None,
+            };
+        }
+
+        let Some(index) = playlist.iter().position(|r| Some(r) == current) else {
+            return playlist.first().cloned();
+        };
+        match playlist.get(index + 1) {
+            Some(next) => Some(next.clone()),
+            None if wrap => playlist.first().cloned(),
+            None => None,
+        }
+    }
+
+    /// Record that playback moved from `previous` to `started`,
+    /// taking `started` off the queue if it was queued next
+    pub fn started(
+        &mut self,
+        started: &AudioResource,
+        previous: Option<AudioResource>,
+        playlist: &[AudioResource],
+    ) {
+        if self.queue.front() == Some(started) {
+            self.queue.pop_front();
+        }
+
+        // Start a new shuffle round once every resource was played. Without repeat
+        // the round ends there, until something is played again by hand
+        if let Some(shuffle) = &mut self.shuffle {
+            let all_played = |played: &[AudioResource]| playlist.iter().all(|r| played.contains(r));
+            if all_played(&shuffle.played) {
+                shuffle.round += 1;
+                shuffle.played.clear();
+            }
+            if !shuffle.played.contains(started) {
+                shuffle.played.push(started.clone());
+            }
+            if self.repeat != RepeatMode::Off && all_played(&shuffle.played) {
+                shuffle.round += 1;
+                shuffle.played = vec![started.clone()];
+            }
+        }
+
+        // Repeating a resource doesn't add it to the history again
+        if let Some(previous) = previous.filter(|p| p != started) {
+            if self.history.last() != Some(&previous) {
+                self.history.push(previous);
+            }
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+    }
+
+    /// Take the most recently played resource off the history
+    pub fn previous(&mut self) -> Option<AudioResource> {
+        self.history.pop()
+    }
+
+    pub fn history(&self) -> &[AudioResource] {
+        &self.history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, time::Duration};
+
+    fn playlist(len: usize) -> Vec<AudioResource> {
+        (0..len)
+            .map(|i| AudioResource::new(PathBuf::from(format!("{}.mp3", i)), Duration::ZERO))
+            .collect()
+    }
+
+    /// Indices of the resources played when playing `first` by hand, then letting autoplay
+    /// continue until `count` resources played
+    fn autoplay(
+        queue: &mut PlayQueue,
+        playlist: &[AudioResource],
+        first: usize,
+        count: usize,
+    ) -> Vec<usize> {
+        let mut current = playlist[first].clone();
+        queue.started(&current, None, playlist);
+        let mut played = vec![first];
+
+        while played.len() < count {
+            let Some(next) = queue.upcoming(Some(&current), playlist) else {
+                break;
+            };
+            queue.started(&next, Some(current), playlist);
+            played.push(playlist.iter().position(|r| *r == next).unwrap());
+            current = next;
+        }
+        played
+    }
+
+    #[test]
+    fn shuffle_order_follows_the_seed() {
+        let playlist = playlist(10);
+        let orders: Vec<Vec<usize>> = [7, 7, 8]
+            .into_iter()
+            .map(|seed| {
+                let mut queue = PlayQueue::default();
+                queue.set_shuffle_seed(seed);
+                autoplay(&mut queue, &playlist, 0, 30)
+            })
+            .collect();
+
+        assert_eq!(orders[0], orders[1]);
+        assert_ne!(orders[0], orders[2]);
+        assert_ne!(orders[0][..10], (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shuffle_plays_everything_before_repeating() {
+        let playlist = playlist(10);
+        let mut queue = PlayQueue::default();
+        queue.set_shuffle_seed(3);
+        let order = autoplay(&mut queue, &playlist, 0, 100);
+        assert_eq!(order.len(), 100);
+
+        // Every round plays each resource once. The rounds after the first start with the
+        // resource that ended the round before, so it isn't played twice in a row
+        for start in (0..=90).step_by(9) {
+            let mut round = order[start..start + 10].to_vec();
+            round.sort();
+            assert_eq!(round, (0..10).collect::<Vec<_>>(), "{:?}", order);
+        }
+        assert!(order.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn shuffle_without_repeat_ends_after_one_round() {
+        let playlist = playlist(10);
+        let mut queue = PlayQueue::default();
+        queue.set_repeat(RepeatMode::Off);
+        queue.set_shuffle_seed(3);
+        assert_eq!(autoplay(&mut queue, &playlist, 4, 100).len(), 10);
+    }
+
+    #[test]
+    fn previous_walks_back_through_the_history() {
+        let playlist = playlist(5);
+        let mut queue = PlayQueue::default();
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(autoplay(&mut queue, &playlist, 0, 5), vec![0, 1, 2, 3, 4]);
+
+        // The playing resource isn't in the history yet
+        let back: Vec<AudioResource> = std::iter::from_fn(|| queue.previous()).collect();
+        assert_eq!(
+            back,
+            playlist[..4].iter().rev().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn repeating_one_resource_keeps_it_out_of_the_history() {
+        let playlist = playlist(5);
+        let mut queue = PlayQueue::default();
+        queue.started(&playlist[1], Some(playlist[0].clone()), &playlist);
+        queue.set_repeat(RepeatMode::One);
+
+        assert_eq!(autoplay(&mut queue, &playlist, 1, 4), vec![1, 1, 1, 1]);
+        assert_eq!(queue.history(), &playlist[..1]);
+    }
+
+    #[test]
+    fn removed_resources_are_forgotten() {
+        let mut playlist = playlist(5);
+        let mut queue = PlayQueue::default();
+        queue.set_shuffle_seed(3);
+        let order = autoplay(&mut queue, &playlist, 0, 3);
+        queue.add(playlist[order[0]].clone());
+
+        let removed = playlist.remove(order[0]);
+        queue.remove_resource(&removed);
+        assert!(queue.is_empty());
+        assert!(!queue.history().contains(&removed));
+        assert!(queue
+            .shuffle
+            .as_ref()
+            .is_some_and(|s| !s.played.contains(&removed)));
+    }
+
+    #[test]
+    fn skipping_from_outside_the_playlist_starts_it() {
+        let playlist = playlist(5);
+        let elsewhere = AudioResource::new(PathBuf::from("elsewhere.mp3"), Duration::ZERO);
+        let mut queue = PlayQueue::default();
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(
+            queue.skip_target(Some(&elsewhere), &playlist),
+            Some(playlist[0].clone())
+        );
+        assert_eq!(
+            queue.skip_target(None, &playlist),
+            Some(playlist[0].clone())
+        );
+        assert_eq!(queue.skip_target(Some(&elsewhere), &[]), None);
+
+        queue.set_shuffle_seed(3);
+        let next = queue.skip_target(Some(&elsewhere), &playlist);
+        assert!(next.is_some_and(|next| playlist.contains(&next)));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source};
+
+/// Where playback of the current resource is, as counted from the samples
+/// that have actually been handed to the output device.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlaybackPosition {
+    pub elapsed: Duration,
+    pub total: Duration,
+}
+
+impl PlaybackPosition {
+    pub fn remaining(&self) -> Duration {
+        self.total.saturating_sub(self.elapsed)
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+        f32::clamp(
+            self.elapsed.as_secs_f32() / self.total.as_secs_f32(),
+            0.0,
+            1.0,
+        )
+    }
+
+    pub fn formatted(&self) -> String {
+        format!("{}/{}", mmss(self.elapsed), mmss(self.total))
+    }
+}
+
+pub fn mmss(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Shared between the player and the `Counted` sources living on the audio thread.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PlaybackClock {
+    elapsed_ns: Arc<AtomicU64>,
+    finished: Arc<AtomicBool>,
+    // Id of the `Counted` source that produced the last sample
+    source: Arc<AtomicU64>,
+}
+
+impl PlaybackClock {
+    pub fn reset(&self, offset: Duration) {
+        self.elapsed_ns
+            .store(offset.as_nanos() as u64, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Relaxed);
+    }
+
+    /// Id of the source that is playing, it changes on the first sample of a queued source
+    pub fn source(&self) -> u64 {
+        self.source.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns.load(Ordering::Relaxed))
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts the samples pulled from `input` and reports them to a `PlaybackClock`.
+pub(crate) struct Counted<S> {
+    input: S,
+    id: u64,
+    offset_ns: u64,
+    frames_ns: u64,
+    samples: u64,
+    clock: PlaybackClock,
+    started: bool,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<S: Source> Counted<S>
+where
+    S::Item: Sample,
+{
+    /// `offset` is where in the track `input` starts, e.g. after a seek.
+    /// Takes over the clock right away, for sources that play immediately.
+    pub fn new(input: S, id: u64, offset: Duration, clock: PlaybackClock) -> Self {
+        clock.reset(offset);
+        clock.source.store(id, Ordering::Relaxed);
+        Self {
+            offset_ns: offset.as_nanos() as u64,
+            started: true,
+            ..Self::queued(input, id, clock)
+        }
+    }
+
+    /// For sources queued behind the playing one, which take over the clock
+    /// with their first sample.
+    pub fn queued(input: S, id: u64, clock: PlaybackClock) -> Self {
+        Self {
+            input,
+            id,
+            offset_ns: 0,
+            frames_ns: 0,
+            samples: 0,
+            clock,
+            started: false,
+            cancelled: Arc::default(),
+        }
+    }
+
+    /// Setting the flag ends the source early, or skips it if it hasn't started yet.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+}
+
+impl<S: Source> Iterator for Counted<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let Some(sample) = self.input.next() else {
+            if self.started && self.clock.source() == self.id {
+                self.clock.finished.store(true, Ordering::Relaxed);
+            }
+            return None;
+        };
+
+        // The previous source just ended, this is the boundary to the next track
+        if !self.started {
+            self.started = true;
+            self.clock.reset(Duration::from_nanos(self.offset_ns));
+            self.clock.source.store(self.id, Ordering::Relaxed);
+        }
+
+        // A track fading out while the next one fades in no longer owns the clock
+        if self.clock.source() != self.id {
+            return Some(sample);
+        }
+
+        let rate = self.input.sample_rate() as u64 * self.input.channels() as u64;
+        if rate == 0 {
+            return Some(sample);
+        }
+
+        // Fold whole seconds into `frames_ns` so the sample rate may change
+        // between frames without the position jumping
+        self.samples += 1;
+        if self.samples >= rate {
+            self.frames_ns += self.samples * 1_000_000_000 / rate;
+            self.samples = 0;
+        }
+
+        let elapsed = self.offset_ns + self.frames_ns + self.samples * 1_000_000_000 / rate;
+        self.clock.elapsed_ns.store(elapsed, Ordering::Relaxed);
+
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Counted<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio_resource::AudioResource;
+
+/// Name of the playlist sours starts with, and that older single-playlist files are moved into
+pub const DEFAULT_NAME: &str = "Default";
+
+/// Tells playlists apart while sours runs, unlike names and positions it never changes
+pub type PlaylistId = u64;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A named list of resources that remembers its own selection
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Playlist {
+    /// Not saved, every playlist gets a new one when it's created or loaded
+    #[serde(skip)]
+    pub id: PlaylistId,
+    pub name: String,
+    pub resources: Vec<AudioResource>,
+    pub selected: Option<AudioResource>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self {
+            id: next_id(),
+            name: String::new(),
+            resources: Vec::new(),
+            selected: None,
+        }
+    }
+}
+
+impl Playlist {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn contains(&self, resource: &AudioResource) -> bool {
+        self.resources.contains(resource)
+    }
+
+    /// Remove every entry of `resource`, deselecting it if it was selected
+    pub fn remove(&mut self, resource: &AudioResource) {
+        self.resources.retain(|r| r != resource);
+        if self.selected.as_ref() == Some(resource) {
+            self.selected = None;
+        }
+    }
+}
+
+/// An id no other playlist has
+pub fn next_id() -> PlaylistId {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// `name`, or `name` with the lowest number that makes it unique among `playlists`
+pub fn unique_name(name: &str, playlists: &[Playlist]) -> String {
+    let taken = |candidate: &str| playlists.iter().any(|p| p.name == candidate);
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} {}", name, n))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loaded_playlists_get_their_own_ids() {
+        let json = r#"[{"name": "a"}, {"name": "b", "resources": []}]"#;
+        let playlists: Vec<Playlist> = serde_json::from_str(json).unwrap();
+        assert_ne!(playlists[0].id, playlists[1].id);
+        assert_ne!(playlists[0].id, Playlist::new("a").id);
+
+        let saved = serde_json::to_string(&playlists[0]).unwrap();
+        assert!(!saved.contains("id"), "{}", saved);
+    }
+}
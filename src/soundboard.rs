@@ -0,0 +1,269 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio_resource::AudioResource;
+use crate::error::SoursError;
+use crate::output_device;
+
+/// Maximum amount of clips playing at once, when exceeded the oldest clip is cut off
+const MAX_VOICES: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Pad {
+    pub resource: AudioResource,
+    pub volume: u8,
+}
+
+impl Pad {
+    pub fn new(resource: AudioResource) -> Self {
+        Self {
+            resource,
+            volume: 100,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.resource.path.file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+/// An extra output device pads are played to, e.g. a virtual microphone
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutputRoute {
+    pub device: String,
+    pub gain: u8,
+    pub enabled: bool,
+}
+
+impl OutputRoute {
+    pub fn new(device: String) -> Self {
+        Self {
+            device,
+            gain: 100,
+            enabled: true,
+        }
+    }
+}
+
+struct Route {
+    device: Option<String>,
+    gain: f32,
+    handle: rodio::OutputStreamHandle,
+    // Extra routes own their stream, the monitor route borrows the player's
+    _stream: Option<rodio::OutputStream>,
+}
+
+struct Voice {
+    resource: AudioResource,
+    pad_volume: u8,
+    // One sink per route, in the order of `Soundboard::routes`
+    sinks: Vec<rodio::Sink>,
+}
+
+impl Voice {
+    fn finished(&self) -> bool {
+        self.sinks.iter().all(|s| s.empty())
+    }
+}
+
+pub struct Soundboard {
+    voices: Vec<Voice>,
+    idle: Vec<Vec<rodio::Sink>>,
+    master_volume: f32,
+    monitor: Option<Route>,
+    extra: Vec<Route>,
+}
+
+impl Soundboard {
+    pub fn new(stream_handle: Option<rodio::OutputStreamHandle>) -> Self {
+        let mut soundboard = Self {
+            voices: Vec::new(),
+            idle: Vec::new(),
+            master_volume: 1.0,
+            monitor: None,
+            extra: Vec::new(),
+        };
+        soundboard.set_stream_handle(stream_handle);
+        soundboard
+    }
+    pub fn set_stream_handle(&mut self, stream_handle: Option<rodio::OutputStreamHandle>) {
+        /*
+            Sinks are bound to their output, so every clip is dropped when switching
+        */
+        self.voices.clear();
+        self.idle.clear();
+        self.monitor = stream_handle.map(|handle| Route {
+            device: None,
+            gain: 1.0,
+            handle,
+            _stream: None,
+        });
+    }
+    pub fn set_routes(&mut self, routes: &[OutputRoute]) -> Vec<String> {
+        /*
+            Open a stream for every enabled route, in addition to the monitor output.
+            Returns a message for every route whose device couldn't be opened
+        */
+        self.voices.clear();
+        self.idle.clear();
+        self.extra.clear();
+
+        let mut errors = Vec::new();
+        for route in routes.iter().filter(|r| r.enabled) {
+            match output_device::open_output(Some(&route.device)) {
+                Ok((stream, handle)) => self.extra.push(Route {
+                    device: Some(route.device.clone()),
+                    gain: f32::clamp(route.gain as f32 / 100.0, 0.0, 1.0),
+                    handle,
+                    _stream: Some(stream),
+                }),
+                Err(e) => errors.push(format!("{}: {}", route.device, e)),
+            }
+        }
+        errors
+    }
+    pub fn set_route_gain_100(&mut self, device: &str, gain: u8) {
+        for route in self
+            .extra
+            .iter_mut()
+            .filter(|r| r.device.as_deref() == Some(device))
+        {
+            route.gain = f32::clamp(gain as f32 / 100.0, 0.0, 1.0);
+        }
+        self.update_volumes();
+    }
+    pub fn play(&mut self, pad: &Pad) -> Result<(), SoursError> {
+        /*
+            Start `pad` on a free set of sinks, clips already playing keep playing
+        */
+        if !pad.resource.playable() || self.routes().next().is_none() {
+            return Ok(());
+        }
+        self.collect_finished();
+
+        // Open every route's decoder up front so a broken file doesn't occupy sinks
+        let decoders = self
+            .routes()
+            .map(|_| pad.resource.try_decoder())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sinks = if let Some(sinks) = self.idle.pop() {
+            sinks
+        } else if self.voices.len() < MAX_VOICES {
+            let sinks: Result<Vec<rodio::Sink>, _> = self
+                .routes()
+                .map(|route| rodio::Sink::try_new(&route.handle))
+                .collect();
+            match sinks {
+                Ok(sinks) => sinks,
+                Err(_) => return Ok(()),
+            }
+        } else {
+            // Every sink is busy, cut off the oldest clip
+            let oldest = self.voices.remove(0);
+            for sink in &oldest.sinks {
+                sink.clear();
+            }
+            oldest.sinks
+        };
+
+        for ((sink, route), decoder) in sinks.iter().zip(self.routes()).zip(decoders) {
+            sink.set_volume(self.sink_volume(route, pad.volume));
+            sink.append(decoder);
+            sink.play();
+        }
+
+        self.voices.push(Voice {
+            resource: pad.resource.clone(),
+            pad_volume: pad.volume,
+            sinks,
+        });
+        Ok(())
+    }
+    pub fn stop(&mut self, resource: &AudioResource) {
+        for voice in self.voices.iter().filter(|v| &v.resource == resource) {
+            for sink in &voice.sinks {
+                sink.clear();
+            }
+        }
+        self.collect_finished();
+    }
+    pub fn stop_all(&mut self) {
+        for voice in &self.voices {
+            for sink in &voice.sinks {
+                sink.clear();
+            }
+        }
+        self.collect_finished();
+    }
+    pub fn is_playing(&self, resource: &AudioResource) -> bool {
+        self.voices
+            .iter()
+            .any(|v| &v.resource == resource && !v.finished())
+    }
+    pub fn playing_count(&self) -> usize {
+        self.voices.iter().filter(|v| !v.finished()).count()
+    }
+    pub fn set_pad_volume(&mut self, pad: &Pad) {
+        /*
+            Apply a changed pad volume to the clips of that pad which are still playing
+        */
+        for voice in self
+            .voices
+            .iter_mut()
+            .filter(|v| v.resource == pad.resource)
+        {
+            voice.pad_volume = pad.volume;
+        }
+        self.update_volumes();
+    }
+    pub fn set_master_volume_100(&mut self, volume: u8) {
+        self.master_volume = f32::clamp(volume as f32 / 100.0, 0.0, 1.0);
+        self.update_volumes();
+    }
+    fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.monitor.iter().chain(self.extra.iter())
+    }
+    fn sink_volume(&self, route: &Route, pad_volume: u8) -> f32 {
+        /*
+            The app's volume only applies to the monitor,
+            extra routes like a virtual microphone only use their own gain
+        */
+        let pad = f32::clamp(pad_volume as f32 / 100.0, 0.0, 1.0);
+        match route.device {
+            None => pad * route.gain * self.master_volume,
+            Some(_) => pad * route.gain,
+        }
+    }
+    fn update_volumes(&self) {
+        for voice in &self.voices {
+            for (sink, route) in voice.sinks.iter().zip(self.routes()) {
+                sink.set_volume(self.sink_volume(route, voice.pad_volume));
+            }
+        }
+    }
+    fn collect_finished(&mut self) {
+        /*
+            Move sinks of finished clips back into the idle pool
+        */
+        let (finished, playing): (Vec<Voice>, Vec<Voice>) =
+            self.voices.drain(..).partition(|v| v.finished());
+        self.voices = playing;
+        self.idle.extend(finished.into_iter().map(|v| v.sinks));
+    }
+}
+
+impl Debug for Soundboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Soundboard")
+            .field("playing", &self.playing_count())
+            .field("idle", &self.idle.len())
+            .field("routes", &self.routes().count())
+            .field("master_volume", &self.master_volume)
+            .finish()
+    }
+}
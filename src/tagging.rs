@@ -0,0 +1,344 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use lofty::{Accessor, ItemKey, Picture, PictureType, Tag, TagExt, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SoursError;
+use crate::ytdlp::DownloadedFile;
+
+/// Separators between the artist and the title in titles like "Artist - Title"
+const ARTIST_SEPARATORS: [&str; 3] = [" - ", " – ", " — "];
+
+/// Suffix of the channels YouTube generates for artists, "Artist - Topic"
+const TOPIC_SUFFIX: &str = " - Topic";
+
+/// What sours writes into the files it downloaded
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TagSettings {
+    /// Whether downloaded files are tagged at all
+    pub enabled: bool,
+    /// Embed the video's thumbnail as the front cover
+    pub embed_thumbnail: bool,
+    /// Take the artist from titles like "Artist - Title" instead of from the uploader
+    pub split_artist: bool,
+    /// Bracketed parts of titles matching one of these are removed, see `clean_title`
+    pub title_rules: Vec<String>,
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        let rules = [
+            "official*",
+            "lyric*",
+            "audio",
+            "video",
+            "music video",
+            "visuali*er",
+            "hd",
+            "hq",
+            "4k",
+        ];
+        Self {
+            enabled: true,
+            embed_thumbnail: true,
+            split_artist: true,
+            title_rules: rules.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+}
+
+/// `title` without the parts in `()` or `[]` that match one of `rules`,
+/// e.g. "official*" removes "(Official Video)" and "[OFFICIAL AUDIO]".
+/// Rules are compared case-insensitively, `*` stands for any text
+pub fn clean_title(title: &str, rules: &[String]) -> String {
+    let mut cleaned = String::with_capacity(title.len());
+    let mut rest = title;
+
+    while let Some(start) = rest.find(['(', '[']) {
+        let close = match rest[start..].starts_with('(') {
+            true => ')',
+            false => ']',
+        };
+        let Some(length) = rest[start + 1..].find(close) else {
+            break;
+        };
+        let end = start + 1 + length;
+        let inside = rest[start + 1..end].trim();
+
+        cleaned.push_str(&rest[..start]);
+        let removed = rules
+            .iter()
+            .map(|rule| rule.trim())
+            .any(|rule| !rule.is_empty() && matches(rule, inside));
+        if !removed {
+            cleaned.push_str(&rest[start..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    cleaned.push_str(rest);
+
+    // Removed parts leave double spaces, or a dangling separator at the end
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    cleaned
+        .trim_end_matches(|c: char| c == '-' || c == '|' || c.is_whitespace())
+        .to_string()
+}
+
+/// Whether `text` matches `pattern` case-insensitively, `*` matching any text
+fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let mut parts = pattern.split('*');
+
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// The artist and the cleaned title of `file`. The artist is parsed from titles like
+/// "Artist - Title" if `settings` allow it, otherwise it's the uploader
+pub fn artist_and_title(
+    file: &DownloadedFile,
+    settings: &TagSettings,
+) -> (Option<String>, Option<String>) {
+    let uploader = file
+        .uploader
+        .as_deref()
+        .map(|uploader| uploader.strip_suffix(TOPIC_SUFFIX).unwrap_or(uploader))
+        .map(String::from);
+    let Some(title) = &file.title else {
+        return (uploader, None);
+    };
+
+    let title = clean_title(title, &settings.title_rules);
+    let split = ARTIST_SEPARATORS
+        .iter()
+        .find_map(|separator| title.split_once(separator))
+        .map(|(artist, title)| (artist.trim(), title.trim()))
+        .filter(|(artist, title)| !artist.is_empty() && !title.is_empty());
+
+    match split {
+        Some((artist, title)) if settings.split_artist => {
+            (Some(artist.to_string()), Some(title.to_string()))
+        }
+        _ => (uploader, Some(title)),
+    }
+}
+
+/// Write what the site said about the video of `file` into its tags. `own_thumbnail` is
+/// whether sours asked yt-dlp for the thumbnail next to it only to embed it, then it's
+/// removed once it was embedded
+pub fn tag_download(
+    file: &DownloadedFile,
+    settings: &TagSettings,
+    own_thumbnail: bool,
+) -> Result<(), SoursError> {
+    if !settings.enabled {
+        return Ok(());
+    }
+    let thumbnail = thumbnail_path(&file.path);
+    write_tags(file, settings, thumbnail.as_deref())?;
+
+    if let Some(thumbnail) = thumbnail.filter(|_| own_thumbnail && settings.embed_thumbnail) {
+        let _ = std::fs::remove_file(thumbnail);
+    }
+    Ok(())
+}
+
+fn write_tags(
+    file: &DownloadedFile,
+    settings: &TagSettings,
+    thumbnail: Option<&Path>,
+) -> Result<(), SoursError> {
+    /*
+        Tags already in the file, e.g. written by yt-dlp, are kept unless sours knows better.
+        The URL goes into the comment too, as not every format has a field for it
+    */
+    let tag_error = |e: lofty::LoftyError| SoursError::Tag(e.to_string());
+    let mut tagged = lofty::read_from_path(&file.path).map_err(tag_error)?;
+    if tagged.primary_tag().is_none() {
+        tagged.insert_tag(Tag::new(tagged.primary_tag_type()));
+    }
+    let Some(tag) = tagged.primary_tag_mut() else {
+        return Ok(());
+    };
+
+    let (artist, title) = artist_and_title(file, settings);
+    if let Some(title) = title {
+        tag.set_title(title);
+    }
+    if let Some(artist) = artist {
+        tag.set_artist(artist);
+    }
+    if let Some(url) = &file.url {
+        tag.insert_text(ItemKey::AudioSourceUrl, url.clone());
+        tag.set_comment(url.clone());
+    }
+    if let Some(date) = file.upload_date.as_deref().and_then(iso_date) {
+        if let Ok(year) = date[..4].parse() {
+            tag.set_year(year);
+        }
+        tag.insert_text(ItemKey::RecordingDate, date);
+    }
+
+    if let Some(thumbnail) = thumbnail.filter(|_| settings.embed_thumbnail) {
+        let mut reader = File::open(thumbnail)?;
+        let mut picture = Picture::from_reader(&mut reader).map_err(tag_error)?;
+        picture.set_pic_type(PictureType::CoverFront);
+        tag.remove_picture_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+    }
+
+    tag.save_to_path(&file.path).map_err(tag_error)
+}
+
+/// yt-dlp's `YYYYMMDD` as `YYYY-MM-DD`
+fn iso_date(date: &str) -> Option<String> {
+    let valid = date.len() == 8 && date.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]))
+}
+
+/// The thumbnail `--write-thumbnail` wrote for `path`, yt-dlp names it like the
+/// video it downloaded, which the audio file only differs from in the extension
+fn thumbnail_path(path: &Path) -> Option<PathBuf> {
+    ["jpg", "png"]
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|thumbnail| thumbnail.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<String> {
+        TagSettings::default().title_rules
+    }
+
+    fn file(title: Option<&str>, uploader: Option<&str>) -> DownloadedFile {
+        DownloadedFile {
+            path: PathBuf::from("/nonexistent/Title.mp3"),
+            title: title.map(String::from),
+            uploader: uploader.map(String::from),
+            video_id: None,
+            thumbnail: None,
+            url: None,
+            upload_date: None,
+        }
+    }
+
+    #[test]
+    fn titles_lose_their_matching_brackets() {
+        let cases = [
+            ("Song (Official Video)", "Song"),
+            ("Song [HD]", "Song"),
+            ("Song (OFFICIAL MUSIC VIDEO) [4K]", "Song"),
+            ("Song [Official Lyric Video] - ", "Song"),
+            ("Song (Visualiser) | ", "Song"),
+            ("Song (Live) [hq]", "Song (Live)"),
+            ("Song (feat. Someone)", "Song (feat. Someone)"),
+            ("Song (Official", "Song (Official"),
+            ("(Audio)  Song  (Video)", "Song"),
+        ];
+        for (title, cleaned) in cases {
+            assert_eq!(clean_title(title, &rules()), cleaned, "{}", title);
+        }
+        assert_eq!(clean_title("Song (Video)", &[]), "Song (Video)");
+        assert_eq!(
+            clean_title("Song (Video)", &[" ".to_string()]),
+            "Song (Video)"
+        );
+    }
+
+    #[test]
+    fn stars_match_any_text() {
+        assert!(matches("official*", "Official Video"));
+        assert!(matches("official*", "official"));
+        assert!(matches("visuali*er", "Visualizer"));
+        assert!(matches("visuali*er", "visualiser"));
+        assert!(matches("*video", "Music Video"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(matches("*", "anything"));
+        assert!(!matches("a*b*c", "acb"));
+        assert!(!matches("a*bc", "abc-"));
+        assert!(!matches("ab*ba", "aba"));
+        assert!(!matches("audio", "audio track"));
+        assert!(!matches("official*", "unofficial"));
+    }
+
+    #[test]
+    fn artists_are_split_from_the_title() {
+        let settings = TagSettings::default();
+        let split = |title, uploader| artist_and_title(&file(title, uploader), &settings);
+
+        assert_eq!(
+            split(Some("Artist - Title (Official Video)"), Some("Label")),
+            (Some("Artist".to_string()), Some("Title".to_string()))
+        );
+        assert_eq!(
+            split(Some("Artist – Title"), None),
+            (Some("Artist".to_string()), Some("Title".to_string()))
+        );
+        assert_eq!(
+            split(Some("Title"), Some("Artist - Topic")),
+            (Some("Artist".to_string()), Some("Title".to_string()))
+        );
+        assert_eq!(
+            split(Some(" - Title"), Some("Uploader")),
+            (Some("Uploader".to_string()), Some("- Title".to_string()))
+        );
+        assert_eq!(
+            split(None, Some("Uploader")),
+            (Some("Uploader".to_string()), None)
+        );
+
+        let settings = TagSettings {
+            split_artist: false,
+            ..TagSettings::default()
+        };
+        assert_eq!(
+            artist_and_title(&file(Some("Artist - Title [HD]"), Some("Label")), &settings),
+            (
+                Some("Label".to_string()),
+                Some("Artist - Title".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn thumbnails_are_kept_unless_they_were_embedded() {
+        let dir = std::env::temp_dir().join(format!("sours-tagging-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let thumbnail = dir.join("Title.jpg");
+        std::fs::write(&thumbnail, b"").unwrap();
+        let mut file = file(Some("Title"), None);
+        file.path = dir.join("Title.mp3");
+
+        let disabled = TagSettings {
+            enabled: false,
+            ..TagSettings::default()
+        };
+        assert!(tag_download(&file, &disabled, true).is_ok());
+        assert!(thumbnail.exists());
+
+        // The audio file is missing, so nothing could be embedded
+        assert!(tag_download(&file, &TagSettings::default(), true).is_err());
+        assert!(thumbnail.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,41 +1,762 @@
-use std::os::windows::process::CommandExt;
-use std::thread;
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
 
-#[derive(Debug, Default)]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{DownloadError, SoursError};
+use crate::paths;
+use crate::platform::{self, Platform};
+use crate::tagging::{self, TagSettings};
+
+/// Marks the progress lines sours asks yt-dlp for, among the rest of its output
+const PROGRESS_PREFIX: &str = "sours-progress";
+
+/// Marks the lines yt-dlp prints for every file it created
+const FILE_PREFIX: &str = "sours-file";
+
+/// A JSON object per created file, printed once it's in its final place
+const FILE_TEMPLATE: &str = "after_move:sours-file \
+    %(.{filepath,id,title,uploader,thumbnail,webpage_url,upload_date})j";
+
+/// How often it's checked whether yt-dlp exited, once its output ended
+const EXIT_POLL: Duration = Duration::from_millis(50);
+
+/// How many of yt-dlp's last error output lines are kept to tell why it failed
+const STDERR_LINES: usize = 20;
+
+/// The fields of a progress line separated by `|`, the title goes last as it may contain one.
+/// Unknown fields are printed as `NA`
+const PROGRESS_TEMPLATE: &str = "download:sours-progress \
+    %(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|\
+    %(progress.speed)s|%(progress.eta)s|%(info.playlist_index)s|%(info.n_entries)s|%(info.title)s";
+
+pub type DownloadId = u64;
+
+/// The formats yt-dlp can extract the audio to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ExtractFormat {
+    #[default]
+    Mp3,
+    Opus,
+    M4a,
+    Flac,
+}
+
+impl ExtractFormat {
+    pub const ALL: [ExtractFormat; 4] = [Self::Mp3, Self::Opus, Self::M4a, Self::Flac];
+
+    /// As yt-dlp's `--audio-format` takes it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+            Self::M4a => "m4a",
+            Self::Flac => "flac",
+        }
+    }
+
+    /// Whether a bitrate can be chosen, lossless formats have none
+    pub fn is_lossy(&self) -> bool {
+        *self != Self::Flac
+    }
+}
+
+/// How yt-dlp is run, kept in the options
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct YtDlpSettings {
+    /// Path or name of the executable, names are looked up on the `PATH`.
+    /// Empty for the one shipped with sours, or `yt-dlp`
+    pub binary: String,
+    pub format: ExtractFormat,
+    /// In kbit/s, `None` for the best yt-dlp can get. Ignored for lossless formats
+    pub bitrate: Option<u32>,
+    /// Of playlists only the videos from `playlist_start` up to `playlist_end` are downloaded,
+    /// counted from 1
+    pub playlist_start: u32,
+    pub playlist_end: Option<u32>,
+    /// Where the files go inside the downloads directory, in yt-dlp's template syntax
+    pub output_template: String,
+    /// A Netscape cookies file, for videos that need a login
+    pub cookies: Option<PathBuf>,
+    /// Like yt-dlp's `--limit-rate`, e.g. `500K` or `4.2M`. Empty for no limit
+    pub rate_limit: String,
+    /// Passed to yt-dlp as they are, split like a shell would
+    pub extra_args: String,
+    /// What's written into the downloaded files once they're done
+    pub tags: TagSettings,
+}
+
+impl Default for YtDlpSettings {
+    fn default() -> Self {
+        Self {
+            binary: String::new(),
+            format: ExtractFormat::Mp3,
+            bitrate: None,
+            playlist_start: 1,
+            playlist_end: Some(10),
+            output_template: "%(title)s.%(ext)s".to_string(),
+            cookies: None,
+            rate_limit: String::new(),
+            extra_args: String::new(),
+            tags: TagSettings::default(),
+        }
+    }
+}
+
+impl YtDlpSettings {
+    /// The executable as configured, the shipped one or `yt-dlp` if none is
+    pub fn binary(&self) -> PathBuf {
+        match self.binary.trim() {
+            "" => paths::get().yt_dlp.clone(),
+            binary => PathBuf::from(binary),
+        }
+    }
+
+    /// The arguments that make yt-dlp download `url` like this,
+    /// without the ones sours reads its output with
+    fn args(&self, url: &str) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "--extract-audio".into(),
+            "--audio-format".into(),
+            self.format.name().into(),
+        ];
+        if let Some(bitrate) = self.bitrate.filter(|_| self.format.is_lossy()) {
+            args.extend(["--audio-quality".into(), format!("{}K", bitrate).into()]);
+        }
+        args.extend([
+            "--playlist-start".into(),
+            self.playlist_start.max(1).to_string().into(),
+        ]);
+        if let Some(end) = self.playlist_end {
+            args.extend(["--playlist-end".into(), end.to_string().into()]);
+        }
+        let template = match self.output_template.trim() {
+            "" => Self::default().output_template,
+            template => template.to_string(),
+        };
+        args.extend(["-o".into(), paths::get().downloads.join(template).into()]);
+        // A thumbnail to embed, as JPEG as not every format can hold WebP
+        if self.tags.enabled && self.tags.embed_thumbnail {
+            args.extend([
+                "--write-thumbnail".into(),
+                "--convert-thumbnails".into(),
+                "jpg".into(),
+            ]);
+        }
+        if let Some(cookies) = &self.cookies {
+            args.extend(["--cookies".into(), cookies.into()]);
+        }
+        if !self.rate_limit.trim().is_empty() {
+            args.extend(["--limit-rate".into(), self.rate_limit.trim().into()]);
+        }
+        args.extend(split_args(&self.extra_args).into_iter().map(OsString::from));
+        args.push(url.into());
+        args
+    }
+
+    /// Whether the thumbnails yt-dlp writes are only there to be embedded,
+    /// not if the extra arguments ask for them as well
+    fn own_thumbnail(&self) -> bool {
+        self.tags.enabled
+            && self.tags.embed_thumbnail
+            && !split_args(&self.extra_args)
+                .iter()
+                .any(|arg| arg == "--write-thumbnail" || arg == "--write-all-thumbnails")
+    }
+}
+
+/// How far the running download got, as reported by yt-dlp
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    /// 0 to 100, `None` while the size is unknown
+    pub percent: Option<f32>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    pub eta: Option<Duration>,
+    /// Position of the video being downloaded in the playlist, and the playlist's length
+    pub item: Option<(u32, u32)>,
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DownloadState {
+    Queued,
+    Running,
+    Finished,
+    Failed(DownloadError),
+    Cancelled,
+}
+
+impl DownloadState {
+    /// Whether the download still has to run or is running
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub id: DownloadId,
+    pub url: String,
+    /// How yt-dlp was configured when it was queued
+    pub settings: YtDlpSettings,
+    pub state: DownloadState,
+    pub progress: Progress,
+    /// The files it created so far
+    pub files: Vec<DownloadedFile>,
+}
+
+/// A file a download created, and what the site said about its video
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DownloadedFile {
+    #[serde(rename = "filepath")]
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    #[serde(rename = "id")]
+    pub video_id: Option<String>,
+    /// URL of the video's thumbnail
+    pub thumbnail: Option<String>,
+    /// URL of the video's page
+    #[serde(rename = "webpage_url")]
+    pub url: Option<String>,
+    /// As `YYYYMMDD`
+    pub upload_date: Option<String>,
+}
+
+/// A download that ended, kept in the options so it can be looked up and retried
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub url: String,
+    /// Retries download the same videos of a playlist, with the settings of then
+    pub playlist_end: Option<u32>,
+    pub title: Option<String>,
+    pub state: DownloadState,
+    /// When it ended, in seconds since the Unix epoch
+    pub ended: u64,
+}
+
+impl From<&Download> for HistoryEntry {
+    fn from(download: &Download) -> Self {
+        let ended = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            url: download.url.clone(),
+            playlist_end: download.settings.playlist_end,
+            title: download.progress.title.clone(),
+            state: download.state.clone(),
+            ended: ended.as_secs(),
+        }
+    }
+}
+
+/// What happened to the running download, sent from the thread reading yt-dlp's output
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent {
+    Progress(DownloadId, Progress),
+    File(DownloadId, DownloadedFile),
+    /// A file was downloaded but its tags couldn't be written, it's still usable
+    TagFailed(DownloadId, PathBuf, SoursError),
+    Finished(DownloadId),
+    Failed(DownloadId, DownloadError),
+    Cancelled(DownloadId),
+}
+
+// The yt-dlp process of the running download
+struct Running {
+    id: DownloadId,
+    child: Arc<Mutex<Child>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Runs yt-dlp for one queued download after the other
 pub struct Downloader {
-    handle: Option<thread::JoinHandle<()>>,
+    downloads: Vec<Download>,
+    next_id: DownloadId,
+    running: Option<Running>,
+    sender: mpsc::Sender<DownloadEvent>,
+    receiver: mpsc::Receiver<DownloadEvent>,
+    // The version the binary reported, while it's checked the result of that check
+    version: Option<Result<String, DownloadError>>,
+    version_check: Option<mpsc::Receiver<Result<String, DownloadError>>>,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            downloads: Vec::new(),
+            next_id: 0,
+            running: None,
+            sender,
+            receiver,
+            version: None,
+            version_check: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Downloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Downloader")
+            .field("downloads", &self.downloads)
+            .field("running", &self.running.as_ref().map(|r| r.id))
+            .field("version", &self.version)
+            .finish()
+    }
 }
 
 impl Downloader {
-    pub fn download(&mut self, url: String, n: u32) {
-        let mut command = std::process::Command::new(r".\bin\yt-dlp.exe");
-
-        #[cfg(target_os = "windows")]
-        command.creation_flags(0x08000000);
-
-        self.handle = Some(thread::spawn(move || {
-            let _ = command
-                .arg("--extract-audio")
-                .arg("--audio-format")
-                .arg("mp3")
-                .arg("--playlist-end")
-                .arg(format!("{}", n))
-                .arg("-o")
-                .arg(r".\downloads\%(title)s.%(ext)s")
-                .arg(url)
-                .status()
-                .unwrap();
-        }));
-    }
-    pub fn is_finished(&mut self) -> bool {
-        if let Some(handle) = &self.handle {
-            if handle.is_finished() {
-                self.handle = None;
-                return true;
-            } else {
-                return false;
-            }
-        }
-        self.handle.is_none()
+    /// Queue the audio of `url` for download, the way `settings` configure yt-dlp
+    pub fn download(&mut self, url: String, settings: YtDlpSettings) -> DownloadId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.downloads.push(Download {
+            id,
+            url,
+            settings,
+            state: DownloadState::Queued,
+            progress: Progress::default(),
+            files: Vec::new(),
+        });
+        self.start_next();
+        id
+    }
+    /// Take a queued download off the queue, or stop the running one
+    pub fn cancel(&mut self, id: DownloadId) {
+        if let Some(running) = self.running.as_ref().filter(|r| r.id == id) {
+            // The thread reading its output reports it as cancelled once it exited
+            running.cancelled.store(true, Ordering::Relaxed);
+            let mut child = running.child.lock().unwrap();
+            // Once reaped its id may belong to another process already
+            if let Ok(None) = child.try_wait() {
+                let _ = platform::native().kill_tree(&mut child);
+            }
+            return;
+        }
+        if let Some(download) = self.downloads.iter_mut().find(|d| d.id == id) {
+            if download.state == DownloadState::Queued {
+                download.state = DownloadState::Cancelled;
+            }
+        }
+    }
+    /// Apply what happened since the last poll, and start the next download
+    /// once one ended. Returns what happened, e.g. to pick up finished downloads
+    pub fn poll(&mut self) -> Vec<DownloadEvent> {
+        if let Some(version) = self.version_check.as_ref().and_then(|r| r.try_recv().ok()) {
+            self.version = Some(version);
+            self.version_check = None;
+        }
+        let events: Vec<DownloadEvent> = self.receiver.try_iter().collect();
+
+        for event in &events {
+            let (id, state) = match event {
+                DownloadEvent::Progress(id, progress) => {
+                    if let Some(download) = self.download_mut(*id) {
+                        download.progress = progress.clone();
+                    }
+                    continue;
+                }
+                DownloadEvent::File(id, file) => {
+                    if let Some(download) = self.download_mut(*id) {
+                        download.files.push(file.clone());
+                    }
+                    continue;
+                }
+                DownloadEvent::TagFailed(..) => continue,
+                DownloadEvent::Finished(id) => (id, DownloadState::Finished),
+                DownloadEvent::Failed(id, error) => (id, DownloadState::Failed(error.clone())),
+                DownloadEvent::Cancelled(id) => (id, DownloadState::Cancelled),
+            };
+            if let Some(download) = self.download_mut(*id) {
+                download.state = state;
+            }
+            if self.running.as_ref().is_some_and(|r| r.id == *id) {
+                self.running = None;
+            }
+        }
+
+        self.start_next();
+        events
+    }
+    /// Every download since sours started, or since the ended ones were cleared
+    pub fn downloads(&self) -> &[Download] {
+        &self.downloads
+    }
+    pub fn get(&self, id: DownloadId) -> Option<&Download> {
+        self.downloads.iter().find(|d| d.id == id)
+    }
+    /// Forget the downloads that ended
+    pub fn clear_ended(&mut self) {
+        self.downloads.retain(|d| d.state.is_pending());
+    }
+    /// Whether a download is running or queued, checking the binary doesn't count
+    pub fn is_active(&self) -> bool {
+        self.downloads.iter().any(|d| d.state.is_pending())
+    }
+    /// Run `binary --version` in the background, `poll` picks up the result
+    pub fn check_binary(&mut self, binary: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(version(&binary));
+        });
+        self.version = None;
+        self.version_check = Some(receiver);
+    }
+    pub fn is_checking(&self) -> bool {
+        self.version_check.is_some()
+    }
+    /// What the last `check_binary` found, `None` while it runs or if it never ran
+    pub fn version(&self) -> Option<&Result<String, DownloadError>> {
+        self.version.as_ref()
+    }
+    fn download_mut(&mut self, id: DownloadId) -> Option<&mut Download> {
+        self.downloads.iter_mut().find(|d| d.id == id)
+    }
+    fn start_next(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let Some(download) = self
+            .downloads
+            .iter_mut()
+            .find(|d| d.state == DownloadState::Queued)
+        else {
+            return;
+        };
+
+        match Self::spawn(download, self.sender.clone()) {
+            Ok(running) => {
+                download.state = DownloadState::Running;
+                self.running = Some(running);
+            }
+            Err(e) => {
+                // Reported like any other failure, so it's noticed and kept in the history
+                let error = spawn_error(e, download.settings.binary());
+                let _ = self
+                    .sender
+                    .send(DownloadEvent::Failed(download.id, error.clone()));
+                download.state = DownloadState::Failed(error);
+            }
+        }
+    }
+    fn spawn(download: &Download, sender: mpsc::Sender<DownloadEvent>) -> io::Result<Running> {
+        /*
+            Run yt-dlp printing one progress line per update, a thread forwards them
+            and reports how it exited, with its error output telling why it failed
+        */
+        let binary = find_binary(&download.settings.binary())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let mut command = Command::new(binary);
+        platform::native().hide_console(&mut command);
+        platform::native().own_process_group(&mut command);
+
+        // The arguments sours reads the output with go first, so extra ones can't replace them
+        command
+            .arg("--print")
+            .arg(FILE_TEMPLATE)
+            // `--print` makes it quiet, the progress is still wanted
+            .arg("--progress")
+            .arg("--newline")
+            .arg("--progress-template")
+            .arg(PROGRESS_TEMPLATE)
+            .args(download.settings.args(&download.url))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child
+            .stderr
+            .take()
+            .map(|stderr| thread::spawn(|| last_lines(stderr)));
+        let child = Arc::new(Mutex::new(child));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let id = download.id;
+        let tags = download.settings.tags.clone();
+        let own_thumbnail = download.settings.own_thumbnail();
+        let thread_child = child.clone();
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            if let Some(stdout) = stdout {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if let Some(progress) = parse_progress(&line) {
+                        let _ = sender.send(DownloadEvent::Progress(id, progress));
+                    } else if let Some(file) = parse_file(&line) {
+                        // Tagged before it's reported, so it's read with its tags
+                        if let Err(e) = tagging::tag_download(&file, &tags, own_thumbnail) {
+                            let path = file.path.clone();
+                            let _ = sender.send(DownloadEvent::TagFailed(id, path, e));
+                        }
+                        let _ = sender.send(DownloadEvent::File(id, file));
+                    }
+                }
+            }
+
+            // Its output ended, so it exited or is about to
+            let stderr = stderr
+                .and_then(|reader| reader.join().ok())
+                .unwrap_or_default();
+            let status = wait(&thread_child);
+            let event = match status {
+                _ if thread_cancelled.load(Ordering::Relaxed) => DownloadEvent::Cancelled(id),
+                Ok(status) if status.success() => DownloadEvent::Finished(id),
+                Ok(status) => {
+                    DownloadEvent::Failed(id, DownloadError::classify(status.code(), &stderr))
+                }
+                Err(e) => DownloadEvent::Failed(
+                    id,
+                    DownloadError::Other {
+                        code: None,
+                        message: e.to_string(),
+                    },
+                ),
+            };
+            let _ = sender.send(event);
+        });
+
+        Ok(Running {
+            id,
+            child,
+            cancelled,
+        })
+    }
+}
+
+/// Waits for `child` to exit, without holding the lock while it runs so `cancel` can kill it
+fn wait(child: &Mutex<Child>) -> io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.lock().unwrap().try_wait()? {
+            return Ok(status);
+        }
+        thread::sleep(EXIT_POLL);
+    }
+}
+
+/// Where `binary` is, names without a directory are looked up on the `PATH`
+/// like the shell does, also with `.exe` on Windows. `None` if it doesn't exist
+pub fn find_binary(binary: &Path) -> Option<PathBuf> {
+    let with_exe = |path: PathBuf| {
+        let exe = path.with_extension("exe");
+        match path.is_file() {
+            true => Some(path),
+            false => (cfg!(windows) && exe.is_file()).then_some(exe),
+        }
+    };
+    if binary.components().count() > 1 {
+        return with_exe(binary.to_path_buf());
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| with_exe(dir.join(binary)))
+}
+
+/// The version `binary --version` reports, to tell early whether downloads can work
+pub fn version(binary: &Path) -> Result<String, DownloadError> {
+    let found = find_binary(binary).ok_or_else(|| DownloadError::MissingBinary(binary.into()))?;
+    let mut command = Command::new(found);
+    platform::native().hide_console(&mut command);
+    let output = command
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| spawn_error(e, binary.into()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DownloadError::classify(output.status.code(), &stderr));
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(version)
+}
+
+fn spawn_error(e: io::Error, binary: PathBuf) -> DownloadError {
+    match e.kind() {
+        io::ErrorKind::NotFound => DownloadError::MissingBinary(binary),
+        _ => DownloadError::Other {
+            code: None,
+            message: e.to_string(),
+        },
+    }
+}
+
+/// `args` split on whitespace outside of quotes, with the quotes removed,
+/// so arguments with spaces can be written like in a shell
+pub fn split_args(args: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+
+    for c in args.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => split.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    split.extend(current);
+    split
+}
+
+/// The last `STDERR_LINES` lines of `output`
+fn last_lines(output: impl Read) -> String {
+    let mut lines = VecDeque::with_capacity(STDERR_LINES);
+    for line in BufReader::new(output).lines().map_while(Result::ok) {
+        if lines.len() == STDERR_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+    Vec::from(lines).join("\n")
+}
+
+/// The file in a line yt-dlp printed with `FILE_TEMPLATE`, `None` for every other line
+pub fn parse_file(line: &str) -> Option<DownloadedFile> {
+    let json = line.trim().strip_prefix(FILE_PREFIX)?;
+    serde_json::from_str(json.trim()).ok()
+}
+
+/// The progress in a line yt-dlp printed with `PROGRESS_TEMPLATE`,
+/// `None` for every other line
+pub fn parse_progress(line: &str) -> Option<Progress> {
+    let line = line.trim().strip_prefix(PROGRESS_PREFIX)?.trim_start();
+    let mut fields = line.splitn(8, '|');
+    let mut number = || {
+        let field = fields.next()?.trim();
+        field
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+    };
+
+    let downloaded = number();
+    let total = number();
+    let estimate = number();
+    let speed = number();
+    let eta = number();
+    let index = number();
+    let count = number();
+
+    let percent = match (downloaded, total.or(estimate)) {
+        (Some(downloaded), Some(total)) if total > 0.0 => {
+            Some((downloaded / total * 100.0).min(100.0) as f32)
+        }
+        _ => None,
+    };
+    let title = fields
+        .next()
+        .map(str::trim)
+        .filter(|title| !title.is_empty() && *title != "NA");
+
+    Some(Progress {
+        percent,
+        speed,
+        eta: eta.map(Duration::from_secs_f64),
+        item: index.zip(count).map(|(i, n)| (i as u32, n as u32)),
+        title: title.map(String::from),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_lines_are_parsed() {
+        let line = "sours-progress 512|2048|NA|1024.5|3|2|5|Artist - Title";
+        let progress = parse_progress(line).unwrap();
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.speed, Some(1024.5));
+        assert_eq!(progress.eta, Some(Duration::from_secs(3)));
+        assert_eq!(progress.item, Some((2, 5)));
+        assert_eq!(progress.title.as_deref(), Some("Artist - Title"));
+    }
+
+    #[test]
+    fn progress_falls_back_to_the_estimate() {
+        let progress = parse_progress("sours-progress 100|NA|400|NA|NA|NA|NA|NA").unwrap();
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.item, None);
+        assert_eq!(progress.title, None);
+
+        let unknown = parse_progress("sours-progress 100|NA|NA|NA|NA|NA|NA|").unwrap();
+        assert_eq!(unknown.percent, None);
+    }
+
+    #[test]
+    fn titles_may_contain_the_separator() {
+        let progress = parse_progress("sours-progress NA|NA|NA|NA|NA|NA|NA|a | b|c").unwrap();
+        assert_eq!(progress.title.as_deref(), Some("a | b|c"));
+    }
+
+    #[test]
+    fn other_lines_are_no_progress() {
+        assert_eq!(parse_progress("[download]  12.5% of 3.00MiB"), None);
+        assert_eq!(parse_progress(""), None);
+        assert_eq!(parse_file("sours-progress 1|2|3|4|5|6|7|8"), None);
+    }
+
+    #[test]
+    fn file_lines_are_parsed() {
+        let line = r#"sours-file {"filepath": "/d/Title.mp3", "id": "abc", "title": "Title", "uploader": null, "thumbnail": "https://i/abc.jpg", "webpage_url": "https://v/abc", "upload_date": "20240101"}"#;
+        let file = parse_file(line).unwrap();
+        assert_eq!(file.path, PathBuf::from("/d/Title.mp3"));
+        assert_eq!(file.video_id.as_deref(), Some("abc"));
+        assert_eq!(file.title.as_deref(), Some("Title"));
+        assert_eq!(file.uploader, None);
+        assert_eq!(file.url.as_deref(), Some("https://v/abc"));
+        assert_eq!(file.upload_date.as_deref(), Some("20240101"));
+
+        assert_eq!(parse_file("sours-file {not json"), None);
+        assert_eq!(parse_file("[ExtractAudio] Destination: Title.mp3"), None);
+    }
+
+    #[test]
+    fn args_are_split_like_a_shell_does() {
+        assert_eq!(split_args(""), Vec::<String>::new());
+        assert_eq!(split_args("  -x   --y  "), vec!["-x", "--y"]);
+        assert_eq!(
+            split_args(r#"--sleep 5 -o "a b/%(title)s" --match 'x "y"'"#),
+            vec!["--sleep", "5", "-o", "a b/%(title)s", "--match", r#"x "y""#]
+        );
+        assert_eq!(
+            split_args(r#"--empty "" a"b"c"#),
+            vec!["--empty", "", "abc"]
+        );
+    }
+
+    #[test]
+    fn thumbnails_asked_for_by_the_user_are_theirs() {
+        let mut settings = YtDlpSettings::default();
+        assert!(settings.own_thumbnail());
+        settings.extra_args = "--sleep 5 --write-thumbnail".to_string();
+        assert!(!settings.own_thumbnail());
+
+        settings.extra_args.clear();
+        settings.tags.embed_thumbnail = false;
+        assert!(!settings.own_thumbnail());
+        settings.tags.embed_thumbnail = true;
+        settings.tags.enabled = false;
+        assert!(!settings.own_thumbnail());
     }
 }
@@ -0,0 +1,85 @@
+//! Presses the registered chords with `xdotool`, so this needs an X server and is
+//! ignored by default. Run it with `cargo test --test hotkeys -- --ignored`, e.g.
+//! headless with `xvfb-run cargo test --test hotkeys -- --ignored`
+#![cfg(target_os = "linux")]
+
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use sours::{
+    audio_player::PlayerAction,
+    audio_resource::AudioResource,
+    hotkeys::{Binding, HotkeyTarget, Hotkeys},
+};
+
+fn press(chord: &str) {
+    let status = Command::new("xdotool").args(["key", chord]).status();
+    assert!(
+        status.unwrap().success(),
+        "xdotool couldn't press {}",
+        chord
+    );
+}
+
+/// Poll `hotkeys` until `count` targets fired or a while passed, returns them
+fn fired(hotkeys: &Hotkeys, count: usize) -> Vec<HotkeyTarget> {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut targets = Vec::new();
+    while targets.len() < count && Instant::now() < deadline {
+        targets.extend(hotkeys.poll());
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    targets
+}
+
+#[test]
+#[ignore = "needs an X server and xdotool"]
+fn pressed_chords_fire_their_targets() {
+    // There is only one event handler per process, so everything runs in this one test
+    let mut hotkeys = Hotkeys::new(|| {});
+    assert!(hotkeys.available());
+
+    let pad = AudioResource::new(PathBuf::from("/nonexistent/pad.wav"), Duration::ZERO);
+    let bindings = [
+        Binding {
+            chord: "ctrl+shift+F9".to_string(),
+            target: HotkeyTarget::Action(PlayerAction::PlayPause),
+        },
+        Binding {
+            chord: "ctrl+shift+F10".to_string(),
+            target: HotkeyTarget::Pad(Box::new(pad.clone())),
+        },
+        Binding {
+            chord: "ctrl+nokey".to_string(),
+            target: HotkeyTarget::Action(PlayerAction::Stop),
+        },
+    ];
+    let errors = hotkeys.register(&bindings);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].starts_with("ctrl+nokey"));
+
+    press("ctrl+shift+F9");
+    press("ctrl+shift+F10");
+    // Unbound chords and releases don't fire anything
+    press("ctrl+shift+F11");
+    assert_eq!(
+        fired(&hotkeys, 3),
+        vec![
+            HotkeyTarget::Action(PlayerAction::PlayPause),
+            HotkeyTarget::Pad(Box::new(pad)),
+        ]
+    );
+
+    // Registering again replaces the previous chords
+    let errors = hotkeys.register(&bindings[..1]);
+    assert!(errors.is_empty(), "{:?}", errors);
+    press("ctrl+shift+F10");
+    press("ctrl+shift+F9");
+    assert_eq!(
+        fired(&hotkeys, 2),
+        vec![HotkeyTarget::Action(PlayerAction::PlayPause)]
+    );
+}
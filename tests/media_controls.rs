@@ -0,0 +1,98 @@
+//! Talks to sours' MPRIS interface over the session bus, so this is ignored by
+//! default. Run it with `cargo test --test media_controls -- --ignored`, e.g.
+//! headless with `dbus-run-session cargo test --test media_controls -- --ignored`
+#![cfg(target_os = "linux")]
+
+use std::time::{Duration, Instant};
+
+use sours::{
+    audio_player::{AudioPlayer, PlayerAction},
+    platform_media_controls::MediaControls,
+};
+use zbus::{
+    blocking::Connection,
+    zvariant::{OwnedValue, Value},
+};
+
+const NAME: &str = "org.mpris.MediaPlayer2.sours";
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+
+fn get(connection: &Connection, property: &str) -> OwnedValue {
+    let reply = connection
+        .call_method(
+            Some(NAME),
+            PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(PLAYER, property),
+        )
+        .unwrap();
+    reply.body().deserialize().unwrap()
+}
+
+/// Poll `controls` until `count` actions arrived or a while passed, returns them
+fn received(controls: &MediaControls, count: usize) -> Vec<PlayerAction> {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut actions = Vec::new();
+    while actions.len() < count && Instant::now() < deadline {
+        actions.extend(controls.poll());
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    actions
+}
+
+#[test]
+#[ignore = "needs a D-Bus session bus"]
+fn mpris_publishes_the_player_and_forwards_requests() {
+    let mut controls = MediaControls::new(None, || {});
+    assert!(controls.available());
+    let player = AudioPlayer::default();
+    controls.update(&player, 40);
+
+    // The interface is served from another thread, wait until it's on the bus
+    let connection = Connection::session().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while connection
+        .call_method(Some(NAME), PATH, Some(PLAYER), "Play", &())
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "{} didn't show up", NAME);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(received(&controls, 1), vec![PlayerAction::Play]);
+
+    let status = String::try_from(get(&connection, "PlaybackStatus")).unwrap();
+    assert_eq!(status, "Stopped");
+    let volume = f64::try_from(get(&connection, "Volume")).unwrap();
+    assert!((volume - 0.4).abs() < 1e-6, "{}", volume);
+
+    for method in ["PlayPause", "Next", "Previous", "Stop"] {
+        connection
+            .call_method(Some(NAME), PATH, Some(PLAYER), method, &())
+            .unwrap();
+    }
+    connection
+        .call_method(Some(NAME), PATH, Some(PLAYER), "Seek", &(-500_000i64))
+        .unwrap();
+    connection
+        .call_method(
+            Some(NAME),
+            PATH,
+            Some("org.freedesktop.DBus.Properties"),
+            "Set",
+            &(PLAYER, "Volume", Value::from(0.25)),
+        )
+        .unwrap();
+    assert_eq!(
+        received(&controls, 6),
+        vec![
+            PlayerAction::PlayPause,
+            PlayerAction::Next,
+            PlayerAction::Previous,
+            PlayerAction::Stop,
+            PlayerAction::Back(Duration::from_millis(500)),
+            PlayerAction::SetVolume(25),
+        ]
+    );
+}
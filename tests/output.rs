@@ -0,0 +1,170 @@
+//! Plays to real output devices, so these are ignored by default. Run them with
+//! `cargo test --test output -- --ignored` where an output exists, e.g. on a
+//! headless machine with a PulseAudio null sink:
+//!
+//! `pactl load-module module-null-sink sink_name=sours-test`
+//!
+//! The soundboard route plays to the device named by `SOURS_TEST_ROUTE`,
+//! or to the first listed device if it isn't set
+
+use std::time::{Duration, Instant};
+
+use sours::{
+    audio_player::AudioPlayer,
+    audio_resource::AudioResource,
+    output_device::{self, Devices},
+    soundboard::{OutputRoute, Pad, Soundboard},
+};
+
+/// A silent 16 bit mono WAV file of `seconds` length
+fn wav(name: &str, seconds: u32) -> AudioResource {
+    const RATE: u32 = 8000;
+    let len = RATE * seconds * 2;
+    let mut data = Vec::new();
+    data.extend_from_slice(b"RIFF");
+    data.extend_from_slice(&(36 + len).to_le_bytes());
+    data.extend_from_slice(b"WAVEfmt ");
+    data.extend_from_slice(&16u32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&RATE.to_le_bytes());
+    data.extend_from_slice(&(RATE * 2).to_le_bytes());
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&16u16.to_le_bytes());
+    data.extend_from_slice(b"data");
+    data.extend_from_slice(&len.to_le_bytes());
+    data.resize(data.len() + len as usize, 0);
+
+    let path = std::env::temp_dir().join(format!("sours-output-{}-{}", std::process::id(), name));
+    std::fs::write(&path, data).unwrap();
+    AudioResource::new(path, Duration::from_secs(seconds as u64))
+}
+
+fn route_device() -> String {
+    std::env::var("SOURS_TEST_ROUTE").unwrap_or_else(|_| {
+        output_device::output_device_names()
+            .into_iter()
+            .next()
+            .expect("no output device")
+    })
+}
+
+/// Wait until `done` holds, panics after a few seconds
+fn until(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+#[ignore = "needs an audio output device"]
+fn output_devices_are_listed_and_opened() {
+    let names = output_device::output_device_names();
+    assert!(!names.is_empty());
+    assert!(output_device::device_available(&names[0]));
+    assert!(output_device::open_output(Some(&names[0])).is_ok());
+    assert!(output_device::open_output(None).is_ok());
+
+    assert!(!output_device::device_available("sours: no such device"));
+    assert!(output_device::open_output(Some("sours: no such device")).is_err());
+}
+
+#[test]
+#[ignore = "needs an audio output device"]
+fn missing_devices_fall_back_to_the_default() {
+    let (player, result) = AudioPlayer::new(Some("sours: no such device".to_string()));
+    assert!(result.is_err());
+    assert!(player.has_output());
+    assert_eq!(player.device(), None);
+}
+
+#[test]
+#[ignore = "needs an audio output device"]
+fn players_on_the_default_follow_it() {
+    let (mut player, result) = AudioPlayer::new(None);
+    result.unwrap();
+    let devices = Devices::list();
+    assert!(!player.check_device(None, &devices));
+
+    // The system switched to another default, e.g. as the one in use was unplugged
+    let switched = Devices {
+        default: Some("sours: another device".to_string()),
+        ..devices.clone()
+    };
+    assert!(player.check_device(None, &switched));
+    assert!(player.has_output());
+    assert!(!player.check_device(None, &devices));
+
+    // Named devices only change once they are gone
+    let name = route_device();
+    player.set_device(Some(name.clone())).unwrap();
+    assert!(!player.check_device(Some(&name), &switched));
+    let gone = Devices {
+        names: Vec::new(),
+        ..devices
+    };
+    assert!(player.check_device(Some(&name), &gone));
+    assert_eq!(player.device(), None);
+}
+
+#[test]
+#[ignore = "needs an audio output device"]
+fn player_plays_seeks_and_stops() {
+    let resource = wav("player.wav", 3);
+    let (mut player, result) = AudioPlayer::new(None);
+    result.unwrap();
+
+    player.play(resource.clone()).unwrap();
+    assert!(player.is_playing());
+    player.seek(Duration::from_secs(2)).unwrap();
+    until("the seek", || {
+        player.position().unwrap().elapsed >= Duration::from_secs(2)
+    });
+
+    // Switching the device keeps the resource playing
+    let device = route_device();
+    player.set_device(Some(device.clone())).unwrap();
+    assert_eq!(player.device(), Some(device.as_str()));
+    assert!(player.is_playing());
+    assert_eq!(player.current.as_ref(), Some(&resource));
+
+    player.stop();
+    assert!(!player.is_playing());
+    assert!(player.position().is_none());
+    std::fs::remove_file(&resource.path).unwrap();
+}
+
+#[test]
+#[ignore = "needs an audio output device"]
+fn soundboard_plays_to_every_route() {
+    let resource = wav("pad.wav", 2);
+    let (player, result) = AudioPlayer::new(None);
+    result.unwrap();
+    let mut soundboard = Soundboard::new(player.stream_handle().cloned());
+
+    let errors = soundboard.set_routes(&[
+        OutputRoute::new(route_device()),
+        OutputRoute::new("sours: no such device".to_string()),
+    ]);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].starts_with("sours: no such device"));
+
+    let pad = Pad::new(resource.clone());
+    soundboard.play(&pad).unwrap();
+    soundboard.play(&pad).unwrap();
+    assert_eq!(soundboard.playing_count(), 2);
+    assert!(soundboard.is_playing(&resource));
+
+    soundboard.stop(&resource);
+    assert_eq!(soundboard.playing_count(), 0);
+    soundboard.play(&pad).unwrap();
+    soundboard.stop_all();
+    assert!(!soundboard.is_playing(&resource));
+
+    // Played clips give their sinks back once they ended
+    soundboard.play(&pad).unwrap();
+    until("the clip to end", || soundboard.playing_count() == 0);
+    std::fs::remove_file(&resource.path).unwrap();
+}
@@ -0,0 +1,176 @@
+//! Runs the downloader against `fixtures/fake-yt-dlp`, a shell script that
+//! prints what yt-dlp would for the URLs it knows
+#![cfg(unix)]
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use sours::{
+    error::DownloadError,
+    paths::{self, Paths},
+    ytdlp::{DownloadEvent, DownloadId, DownloadState, Downloader, YtDlpSettings},
+};
+
+fn fake_binary() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fake-yt-dlp")
+}
+
+fn settings() -> YtDlpSettings {
+    paths::init(Paths::portable(
+        &std::env::temp_dir().join("sours-ytdlp-test"),
+    ));
+    let mut settings = YtDlpSettings {
+        binary: fake_binary().to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    // Tagging needs real files
+    settings.tags.enabled = false;
+    settings
+}
+
+/// Poll `downloader` until download `id` ended, returns every event until then
+fn until_ended(downloader: &mut Downloader, id: DownloadId) -> Vec<DownloadEvent> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut events = Vec::new();
+    while Instant::now() < deadline {
+        events.extend(downloader.poll());
+        let ended = events.iter().any(|event| match event {
+            DownloadEvent::Finished(ended)
+            | DownloadEvent::Failed(ended, _)
+            | DownloadEvent::Cancelled(ended) => *ended == id,
+            _ => false,
+        });
+        if ended {
+            return events;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    panic!("download {} didn't end, got {:?}", id, events);
+}
+
+/// Poll `downloader` until download `id` reported progress, so its process is running
+fn until_progress(downloader: &mut Downloader, id: DownloadId) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        let events = downloader.poll();
+        let progressed = events.iter().any(
+            |event| matches!(event, DownloadEvent::Progress(progressed, _) if *progressed == id),
+        );
+        if progressed {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    panic!("download {} didn't progress", id);
+}
+
+#[test]
+fn finished_downloads_report_progress_and_files() {
+    let mut downloader = Downloader::default();
+    let id = downloader.download("finish".to_string(), settings());
+    let events = until_ended(&mut downloader, id);
+
+    let progress = events
+        .iter()
+        .find_map(|event| match event {
+            DownloadEvent::Progress(_, progress) => Some(progress),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(progress.percent, Some(50.0));
+    assert_eq!(progress.item, Some((1, 3)));
+    assert_eq!(progress.title.as_deref(), Some("Fake | Title"));
+
+    let download = downloader.get(id).unwrap();
+    assert_eq!(download.state, DownloadState::Finished);
+    assert_eq!(download.files.len(), 1);
+    assert_eq!(
+        download.files[0].path,
+        PathBuf::from("/nonexistent/Fake.mp3")
+    );
+    assert_eq!(download.files[0].video_id.as_deref(), Some("abc"));
+    assert!(!downloader.is_active());
+}
+
+#[test]
+fn failed_downloads_tell_why() {
+    let mut downloader = Downloader::default();
+    let id = downloader.download("unavailable".to_string(), settings());
+    until_ended(&mut downloader, id);
+
+    let expected = DownloadError::Unavailable("[youtube] abc: Video unavailable".to_string());
+    assert_eq!(
+        downloader.get(id).unwrap().state,
+        DownloadState::Failed(expected)
+    );
+}
+
+#[test]
+fn missing_binaries_fail_the_download() {
+    let mut downloader = Downloader::default();
+    let mut settings = settings();
+    settings.binary = "/nonexistent/yt-dlp".to_string();
+    let id = downloader.download("finish".to_string(), settings);
+    until_ended(&mut downloader, id);
+
+    let expected = DownloadError::MissingBinary(PathBuf::from("/nonexistent/yt-dlp"));
+    assert_eq!(
+        downloader.get(id).unwrap().state,
+        DownloadState::Failed(expected)
+    );
+}
+
+#[test]
+fn downloads_run_one_after_the_other_and_can_be_cancelled() {
+    let mut downloader = Downloader::default();
+    let running = downloader.download("hang".to_string(), settings());
+    let queued = downloader.download("finish".to_string(), settings());
+    let cancelled = downloader.download("finish".to_string(), settings());
+
+    assert_eq!(
+        downloader.get(running).unwrap().state,
+        DownloadState::Running
+    );
+    assert_eq!(downloader.get(queued).unwrap().state, DownloadState::Queued);
+    downloader.cancel(cancelled);
+    assert_eq!(
+        downloader.get(cancelled).unwrap().state,
+        DownloadState::Cancelled
+    );
+
+    // Only ends in time if the child holding on to the output was killed too
+    until_progress(&mut downloader, running);
+    downloader.cancel(running);
+    until_ended(&mut downloader, running);
+    assert_eq!(
+        downloader.get(running).unwrap().state,
+        DownloadState::Cancelled
+    );
+
+    until_ended(&mut downloader, queued);
+    assert_eq!(
+        downloader.get(queued).unwrap().state,
+        DownloadState::Finished
+    );
+    assert_eq!(
+        downloader.get(cancelled).unwrap().state,
+        DownloadState::Cancelled
+    );
+}
+
+#[test]
+fn checking_the_binary_reports_its_version() {
+    let mut downloader = Downloader::default();
+    downloader.check_binary(fake_binary());
+    assert!(downloader.is_checking());
+    assert!(!downloader.is_active());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while downloader.is_checking() && Instant::now() < deadline {
+        downloader.poll();
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(downloader.version(), Some(&Ok("2024.01.01".to_string())));
+}